@@ -0,0 +1,232 @@
+// Post-game analysis: every move of the game is graded like a coached move, then summed up per side
+// as average centipawn loss and accuracy, and written out as annotated PGN or JSON.
+//
+// Accuracy follows the lichess formulas: centipawns become a winning chance, and the drop in winning
+// chance caused by a move becomes its accuracy.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::*;
+use crate::bot::SearchLimits;
+use crate::review::{self,MoveQuality,MoveReview};
+use crate::rng::{self,Rng};
+use crate::syzygy::Tablebase;
+use crate::eval::Evaluator;
+use crate::score::Score;
+use crate::pgn;
+
+// a single move never counts for more than this in the average loss
+const MAX_CENTIPAWN_LOSS:i32 = 1000;
+// a drop in winning chance (percent) that makes a move a turning point of the game
+const CRITICAL_DROP:f32 = 10.;
+
+fn move_accuracy(win_before:f32,win_after:f32)->f32{
+    (103.1668 * (-0.04354 * (win_before - win_after).max(0.)).exp() - 3.1669).clamp(0.,100.)
+}
+
+#[derive(Clone,Debug)]
+pub struct AnalyzedMove{
+    pub ply:usize,
+    pub white:bool,
+    pub review:MoveReview,
+    // for white, after the move
+    pub eval:Score,
+    pub centipawn_loss:f32,
+    pub accuracy:f32,
+    pub critical:bool,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone,Debug)]
+pub struct GameAnalysis{
+    moves:Vec<AnalyzedMove>,
+    start:Board,
+    result:String,
+    white_acpl:f32,
+    black_acpl:f32,
+    white_accuracy:f32,
+    black_accuracy:f32,
+}
+
+pub(crate) fn analyze(history:&[(Board,PossibleMove)],end:&Board,tablebase:&Tablebase,evaluator:&Evaluator,limits:&SearchLimits,seed:u64)->GameAnalysis{
+    let mut moves = vec![];
+    for (ply,(board,mov)) in history.iter().enumerate(){
+        let mut rng = Rng::new(rng::position_seed(seed,board));
+        let review = review::review_move(board,mov,tablebase,evaluator,limits,&mut rng);
+        let white = board.counter % 2 == 0;
+        let win_before = review.best_score.win_probability() * 100.;
+        let win_after = review.played_score.win_probability() * 100.;
+        moves.push(AnalyzedMove{
+            ply,
+            white,
+            eval:if white {review.played_score} else {-review.played_score},
+            centipawn_loss:review.loss.min(MAX_CENTIPAWN_LOSS) as f32,
+            accuracy:move_accuracy(win_before,win_after),
+            critical:win_before - win_after >= CRITICAL_DROP,
+            review,
+        });
+    }
+
+    let side = |white:bool,value:fn(&AnalyzedMove)->f32|{
+        let values:Vec<f32> = moves.iter().filter(|m| m.white == white).map(value).collect();
+        if values.is_empty() {0.} else {values.iter().sum::<f32>() / values.len() as f32}
+    };
+    let (white_acpl,black_acpl) = (side(true,|m| m.centipawn_loss),side(false,|m| m.centipawn_loss));
+    let (white_accuracy,black_accuracy) = (side(true,|m| m.accuracy),side(false,|m| m.accuracy));
+
+    GameAnalysis{
+        start:history.first().map(|(board,_)| *board).unwrap_or(*end),
+        result:pgn::game_result(end).to_string(),
+        moves,
+        white_acpl,
+        black_acpl,
+        white_accuracy,
+        black_accuracy,
+    }
+}
+
+fn nag(quality:MoveQuality)->Option<&'static str>{
+    match quality{
+        MoveQuality::Inaccuracy=>Some("$6"),
+        MoveQuality::Mistake=>Some("$2"),
+        MoveQuality::Blunder=>Some("$4"),
+        _=>None,
+    }
+}
+
+fn json_string(s:&str)->String{
+    let mut res = String::from("\"");
+    for c in s.chars(){
+        match c{
+            '"'=>res += "\\\"",
+            '\\'=>res += "\\\\",
+            c if (c as u32) < 0x20=>res += &format!("\\u{:04x}",c as u32),
+            c=>res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GameAnalysis{
+
+    pub fn white_acpl(&self)->f32{
+        self.white_acpl
+    }
+
+    pub fn black_acpl(&self)->f32{
+        self.black_acpl
+    }
+
+    pub fn white_accuracy(&self)->f32{
+        self.white_accuracy
+    }
+
+    pub fn black_accuracy(&self)->f32{
+        self.black_accuracy
+    }
+
+    // the game with NAGs for inaccuracies, mistakes and blunders and the evaluation after every move
+    pub fn to_pgn(&self)->String{
+        let mut res = String::new();
+        res += "[Event \"Steinfish game analysis\"]\n";
+        res += &format!("[Result \"{}\"]\n",self.result);
+        if self.start.to_fen() != Board::start_fen(){
+            res += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n",self.start.to_fen());
+        }
+        res += &format!("[WhiteACPL \"{:.0}\"]\n[BlackACPL \"{:.0}\"]\n",self.white_acpl,self.black_acpl);
+        res += &format!("[WhiteAccuracy \"{:.1}\"]\n[BlackAccuracy \"{:.1}\"]\n\n",self.white_accuracy,self.black_accuracy);
+
+        let first_number = self.start.counter / 2 + 1;
+        let black_first = self.start.counter % 2 == 1;
+        let mut text = vec![];
+        for m in self.moves.iter(){
+            let number = first_number + (m.ply as i32 + black_first as i32) / 2;
+            // every move is followed by a comment, so black's moves need their number as well
+            if m.white{
+                text.push(format!("{}.",number));
+            }else{
+                text.push(format!("{}...",number));
+            }
+            text.push(m.review.san.clone());
+            text.extend(nag(m.review.quality).map(str::to_string));
+            let eval = match m.eval.mate_moves(){
+                Some(moves)=>format!("#{}",moves),
+                None=>format!("{:.2}",m.eval.raw() as f32 / 100.),
+            };
+            let mut comment = format!("[%eval {}]",eval);
+            if m.review.quality != MoveQuality::Best && m.review.quality != MoveQuality::Good{
+                comment += &format!(" {:?}. {} was best.",m.review.quality,m.review.best_san);
+            }
+            if m.critical{
+                comment += " Critical moment.";
+            }
+            text.push(format!("{{{}}}",comment));
+        }
+        text.push(self.result.clone());
+        res + &pgn::wrap(text)
+    }
+
+    pub fn to_json(&self)->String{
+        let moves:Vec<String> = self.moves.iter().map(|m| format!(
+            "{{\"ply\":{},\"san\":{},\"quality\":{},\"best\":{},\"eval\":{},\"evalText\":{},\"centipawnLoss\":{:.0},\"accuracy\":{:.1},\"critical\":{}}}",
+            m.ply,json_string(&m.review.san),json_string(&format!("{:?}",m.review.quality).to_lowercase()),
+            json_string(&m.review.best_san),m.eval.raw(),json_string(&m.eval.text()),m.centipawn_loss,m.accuracy,m.critical,
+        )).collect();
+        format!(
+            "{{\"result\":{},\"white\":{{\"acpl\":{:.1},\"accuracy\":{:.1}}},\"black\":{{\"acpl\":{:.1},\"accuracy\":{:.1}}},\"moves\":[{}]}}",
+            json_string(&self.result),self.white_acpl,self.white_accuracy,self.black_acpl,self.black_accuracy,moves.join(","),
+        )
+    }
+}
+
+impl GameAnalysis{
+
+    pub fn moves(&self)->&[AnalyzedMove]{
+        &self.moves
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn analysis_of(fen:&str,names:&[&str])->GameAnalysis{
+        let mut board = Board::from_fen(fen).unwrap();
+        let mut history = vec![];
+        for name in names{
+            let mov = board.parse_move(name).unwrap();
+            let next = board.play_move(&mov);
+            history.push((board,mov));
+            board = next;
+        }
+        analyze(&history,&board,&Tablebase::new(),&Evaluator::default(),&SearchLimits::new(50,1,0),1)
+    }
+
+    fn result_of(fen:&str,names:&[&str])->String{
+        analysis_of(fen,names).result
+    }
+
+    #[test]
+    fn result_from_the_final_position(){
+        assert_eq!(result_of(Board::start_fen(),&["f3","e5","g4","Qh4"]),"0-1");
+        assert_eq!(result_of("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",&["Ra8"]),"1-0");
+        assert_eq!(result_of("7k/8/6K1/5Q2/8/8/8/8 w - - 0 1",&["Qf7"]),"1/2-1/2");
+        assert_eq!(result_of(Board::start_fen(),&["e4"]),"*");
+    }
+
+    #[test]
+    fn pgn_move_numbers(){
+        let numbers = |pgn:&str| pgn.split_whitespace()
+            .filter(|token| token.ends_with('.') && token.starts_with(|c:char| c.is_ascii_digit()))
+            .map(str::to_string).collect::<Vec<_>>();
+        let pgn = analysis_of(Board::start_fen(),&["f3","e5","g4","Qh4"]).to_pgn();
+        assert_eq!(numbers(&pgn),["1.","1...","2.","2..."]);
+        assert!(pgn.trim_end().ends_with("0-1"));
+
+        let pgn = analysis_of("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",&["e5","Nf3","Nc6"]).to_pgn();
+        assert_eq!(numbers(&pgn),["1...","2.","2..."]);
+    }
+}
@@ -0,0 +1,561 @@
+//! The engine as a Rust library, independent of the bindings for the webapp.
+//!
+//! A [`Board`] is a game in progress: it lists the legal [`Move`]s, plays and takes them back and
+//! reports check, mate and stalemate. An [`Engine`] searches a board for the best move.
+//!
+//! ```
+//! use chess_engine::api::{Board,Engine,Status};
+//! use chess_engine::SearchLimits;
+//!
+//! let mut board = Board::new();
+//! for name in ["f3","e5","g4","Qh4#"]{
+//!     let mov = board.parse_move(name).unwrap();
+//!     board.make_move(mov).unwrap();
+//! }
+//! assert!(matches!(board.status(),Status::Checkmate{..}));
+//! board.unmake_move();
+//! let best = Engine::new().search(&board,&SearchLimits::new(200,2,0)).unwrap();
+//! assert_eq!(best.best_move.to_string(),"d8h4");
+//! ```
+//!
+//! Squares are numbered a1 = 0, b1 = 1 up to h8 = 63 and moves are named in coordinate notation
+//! ("e2e4", "e7e8q", castling as the king's move "e1g1").
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bot::{self,Difficulty,Reason,SearchLimits,Strength,MIN_EXPANSIONS};
+use crate::eval::Evaluator;
+use crate::mate::find_mate_within;
+use crate::pgn;
+use crate::rng::Rng;
+use crate::score::Score;
+use crate::syzygy::Tablebase;
+use crate::tablegen::{all_moves,is_promotion};
+use crate::{PossibleMove,Tile};
+
+/// One side of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color{
+    White,
+    Black,
+}
+
+impl Color{
+
+    /// The opponent.
+    pub fn other(self)->Color{
+        match self{
+            Color::White=>Color::Black,
+            Color::Black=>Color::White,
+        }
+    }
+
+    fn from_engine(color:crate::Color)->Color{
+        match color{
+            crate::Color::White=>Color::White,
+            crate::Color::Black=>Color::Black,
+        }
+    }
+}
+
+/// A kind of piece, without its colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Piece{
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl Piece{
+
+    /// The FEN letter, uppercase for white.
+    pub fn to_char(self,color:Color)->char{
+        let c = match self{
+            Piece::Pawn=>'p',
+            Piece::Knight=>'n',
+            Piece::Bishop=>'b',
+            Piece::Rook=>'r',
+            Piece::Queen=>'q',
+            Piece::King=>'k',
+        };
+        if color == Color::White {c.to_ascii_uppercase()} else {c}
+    }
+
+    /// Reads a FEN letter, its case gives the colour.
+    pub fn from_char(c:char)->Option<(Color,Piece)>{
+        let piece = match c.to_ascii_lowercase(){
+            'p'=>Piece::Pawn,
+            'n'=>Piece::Knight,
+            'b'=>Piece::Bishop,
+            'r'=>Piece::Rook,
+            'q'=>Piece::Queen,
+            'k'=>Piece::King,
+            _=>return None,
+        };
+        Some((if c.is_ascii_uppercase() {Color::White} else {Color::Black},piece))
+    }
+
+    fn from_engine(piece:crate::Piece)->Piece{
+        match piece{
+            crate::Piece::Pawn=>Piece::Pawn,
+            crate::Piece::Knight=>Piece::Knight,
+            crate::Piece::Bishop=>Piece::Bishop,
+            crate::Piece::Rook=>Piece::Rook,
+            crate::Piece::Queen=>Piece::Queen,
+            crate::Piece::King=>Piece::King,
+        }
+    }
+
+    // the promotion code of a PossibleMove
+    fn from_upgrade(upgrade:u8)->Piece{
+        match upgrade{
+            1=>Piece::Rook,
+            2=>Piece::Knight,
+            3=>Piece::Bishop,
+            _=>Piece::Queen,
+        }
+    }
+}
+
+/// A square of the board, a1 = 0 to h8 = 63.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+impl Square{
+
+    /// File and rank from 0 (the a-file, the first rank) to 7.
+    pub fn new(file:u8,rank:u8)->Option<Square>{
+        (file < 8 && rank < 8).then_some(Square(rank * 8 + file))
+    }
+
+    /// The square with this number, a1 = 0 to h8 = 63.
+    pub fn from_index(index:usize)->Option<Square>{
+        (index < 64).then_some(Square(index as u8))
+    }
+
+    pub fn index(self)->usize{
+        self.0 as usize
+    }
+
+    /// 0 for the a-file.
+    pub fn file(self)->u8{
+        self.0 % 8
+    }
+
+    /// 0 for the first rank.
+    pub fn rank(self)->u8{
+        self.0 / 8
+    }
+
+    /// All 64 squares, a1 first.
+    pub fn all()->impl Iterator<Item=Square>{
+        (0..64).map(Square)
+    }
+
+    // the engine stores files mirrored, x = 0 is the h-file
+    fn from_engine(num:usize)->Square{
+        Square((num / 8 * 8 + 7 - num % 8) as u8)
+    }
+
+    fn to_engine(self)->usize{
+        self.rank() as usize * 8 + 7 - self.file() as usize
+    }
+}
+
+impl FromStr for Square{
+    type Err = String;
+
+    /// "e4"
+    fn from_str(name:&str)->Result<Square,String>{
+        match name.as_bytes(){
+            [file @ b'a'..=b'h',rank @ b'1'..=b'8']=>Ok(Square((rank - b'1') * 8 + file - b'a')),
+            _=>Err(format!("invalid square {}",name)),
+        }
+    }
+}
+
+impl fmt::Display for Square{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"{}{}",(b'a' + self.file()) as char,self.rank() + 1)
+    }
+}
+
+/// A move by its start and target square, castling is the king's move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Move{
+    from:Square,
+    to:Square,
+    promotion:Option<Piece>,
+}
+
+impl Move{
+
+    /// Whether the move is legal is only known on a board, see [`Board::is_legal`].
+    pub fn new(from:Square,to:Square,promotion:Option<Piece>)->Move{
+        Move{from,to,promotion}
+    }
+
+    pub fn from(self)->Square{
+        self.from
+    }
+
+    pub fn to(self)->Square{
+        self.to
+    }
+
+    /// The piece a pawn becomes on the last rank.
+    pub fn promotion(self)->Option<Piece>{
+        self.promotion
+    }
+
+    fn from_engine(board:&crate::Board,mov:&PossibleMove)->Move{
+        Move{
+            from:Square::from_engine(mov.start.num),
+            to:Square::from_engine(mov.end.num),
+            promotion:is_promotion(board,mov).then(|| Piece::from_upgrade(mov.upgrade)),
+        }
+    }
+}
+
+impl FromStr for Move{
+    type Err = String;
+
+    /// Coordinate notation, "e2e4" or "e7e8q".
+    fn from_str(name:&str)->Result<Move,String>{
+        if !name.is_ascii() || !(4..=5).contains(&name.len()){
+            return Err(format!("invalid move {}",name));
+        }
+        let promotion = match name[4..].chars().next(){
+            None=>None,
+            Some(c)=>match Piece::from_char(c){
+                Some((_,piece)) if !matches!(piece,Piece::Pawn | Piece::King)=>Some(piece),
+                _=>return Err(format!("invalid promotion in {}",name)),
+            },
+        };
+        Ok(Move{from:name[0..2].parse()?,to:name[2..4].parse()?,promotion})
+    }
+}
+
+impl fmt::Display for Move{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"{}{}",self.from,self.to)?;
+        match self.promotion{
+            Some(piece)=>write!(f,"{}",piece.to_char(Color::Black)),
+            None=>Ok(()),
+        }
+    }
+}
+
+/// How the game stands for the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status{
+    Ongoing,
+    Checkmate{winner:Color},
+    Stalemate,
+}
+
+/// A game from some start position, with the moves played so far.
+///
+/// The board keeps no halfmove clock and no repetition count, draws by those rules are not detected.
+#[derive(Clone, Debug)]
+pub struct Board{
+    position:crate::Board,
+    // every move played with the position it was played on
+    history:Vec<(crate::Board,Move)>,
+}
+
+impl Default for Board{
+    fn default()->Board{
+        Board::new()
+    }
+}
+
+impl Board{
+
+    /// The initial position.
+    pub fn new()->Board{
+        Board::from_position(crate::Board::new())
+    }
+
+    pub fn from_fen(fen:&str)->Result<Board,String>{
+        crate::Board::from_fen(fen).map(Board::from_position)
+    }
+
+    /// The start position and main line of the first game in a PGN text.
+    pub fn from_pgn(text:&str)->Result<Board,String>{
+        let (start,moves) = pgn::read_pgn(text)?;
+        let mut board = Board::from_position(start);
+        for mov in moves{
+            let mov = Move::from_engine(&board.position,&mov);
+            board.make_move(mov)?;
+        }
+        Ok(board)
+    }
+
+    /// Wraps a board of the lower level functions like [`crate::choose_move`], without history.
+    pub fn from_position(position:crate::Board)->Board{
+        Board{position,history:vec![]}
+    }
+
+    /// The current position for the lower level functions.
+    pub fn position(&self)->&crate::Board{
+        &self.position
+    }
+
+    pub fn to_fen(&self)->String{
+        self.position.to_fen()
+    }
+
+    /// The game with its result, from the start position.
+    pub fn to_pgn(&self)->String{
+        let moves:Vec<PossibleMove> = self.history.iter().map(|(position,mov)| find(position,*mov).unwrap()).collect();
+        pgn::write_pgn(&self.start(),&moves,&[])
+    }
+
+    // the position before the first move
+    fn start(&self)->crate::Board{
+        self.history.first().map_or(self.position,|(position,_)| *position)
+    }
+
+    pub fn side_to_move(&self)->Color{
+        if self.position.white_to_move() {Color::White} else {Color::Black}
+    }
+
+    /// As in FEN, 1 for the first move of the game.
+    pub fn fullmove_number(&self)->u32{
+        self.position.fullmove_number()
+    }
+
+    pub fn piece_at(&self,square:Square)->Option<(Color,Piece)>{
+        match self.position.data[square.to_engine()]{
+            Tile::Taken(color,piece,_)=>Some((Color::from_engine(color),Piece::from_engine(piece))),
+            Tile::Empty=>None,
+        }
+    }
+
+    /// The legal moves of the side to move, a promotion once per piece.
+    pub fn legal_moves(&self)->impl Iterator<Item=Move> + '_{
+        all_moves(&self.position).into_iter().map(|mov| Move::from_engine(&self.position,&mov))
+    }
+
+    pub fn is_legal(&self,mov:Move)->bool{
+        find(&self.position,mov).is_some()
+    }
+
+    /// A legal move in SAN ("Nf3", "O-O", "e8=Q") or coordinate notation ("g1f3").
+    pub fn parse_move(&self,text:&str)->Result<Move,String>{
+        self.position.parse_move(text).map(|mov| Move::from_engine(&self.position,&mov))
+    }
+
+    /// The move in standard algebraic notation, None if it is not legal here.
+    pub fn san(&self,mov:Move)->Option<String>{
+        find(&self.position,mov).map(|found| self.position.san(&found))
+    }
+
+    /// Plays a legal move, an illegal one leaves the board as it is.
+    pub fn make_move(&mut self,mov:Move)->Result<(),String>{
+        let found = find(&self.position,mov).ok_or_else(|| format!("illegal move {}",mov))?;
+        let next = self.position.play_move(&found);
+        self.history.push((self.position,mov));
+        self.position = next;
+        Ok(())
+    }
+
+    /// Takes back the last move, None at the start position.
+    pub fn unmake_move(&mut self)->Option<Move>{
+        let (position,mov) = self.history.pop()?;
+        self.position = position;
+        Some(mov)
+    }
+
+    /// The moves played since the start position, oldest first.
+    pub fn moves(&self)->impl Iterator<Item=Move> + '_{
+        self.history.iter().map(|(_,mov)| *mov)
+    }
+
+    pub fn last_move(&self)->Option<Move>{
+        self.history.last().map(|(_,mov)| *mov)
+    }
+
+    pub fn in_check(&self)->bool{
+        self.position.in_check()
+    }
+
+    pub fn status(&self)->Status{
+        match pgn::game_result(&self.position){
+            "1-0"=>Status::Checkmate{winner:Color::White},
+            "0-1"=>Status::Checkmate{winner:Color::Black},
+            "1/2-1/2"=>Status::Stalemate,
+            _=>Status::Ongoing,
+        }
+    }
+
+    pub fn is_game_over(&self)->bool{
+        self.status() != Status::Ongoing
+    }
+
+    /// What the move wins or loses in centipawns once the captures on its target square are played
+    /// out, None if it is not legal here.
+    pub fn static_exchange(&self,mov:Move)->Option<i32>{
+        find(&self.position,mov).map(|found| self.position.see(&found))
+    }
+}
+
+impl fmt::Display for Board{
+    /// The position as FEN.
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"{}",self.to_fen())
+    }
+}
+
+// the engine's move on a position
+fn find(position:&crate::Board,mov:Move)->Option<PossibleMove>{
+    all_moves(position).into_iter().find(|found| Move::from_engine(position,found) == mov)
+}
+
+/// The outcome of a search.
+#[derive(Clone, Debug)]
+pub struct SearchResult{
+    pub best_move:Move,
+    /// For the side to move.
+    pub score:Score,
+    /// Whether the move came from the search, a forced mate or the endgame tables.
+    pub reason:Reason,
+    /// The answer the engine expects.
+    pub ponder:Option<Move>,
+}
+
+/// Searches positions with its own endgame tables, evaluation and random numbers.
+pub struct Engine{
+    tablebase:Tablebase,
+    evaluator:Evaluator,
+    rng:Rng,
+}
+
+impl Default for Engine{
+    fn default()->Engine{
+        Engine::new()
+    }
+}
+
+impl Engine{
+
+    /// The handcrafted evaluation, no endgame tables.
+    pub fn new()->Engine{
+        Engine{tablebase:Tablebase::new(),evaluator:Evaluator::default(),rng:Rng::from_entropy()}
+    }
+
+    /// An engine that always answers the same way for the same sequence of searches.
+    pub fn with_seed(seed:u64)->Engine{
+        Engine{rng:Rng::new(seed),..Engine::new()}
+    }
+
+    /// Add syzygy or generated tables here.
+    pub fn tablebase_mut(&mut self)->&mut Tablebase{
+        &mut self.tablebase
+    }
+
+    /// Change the evaluation parameters or load a network here.
+    pub fn evaluator_mut(&mut self)->&mut Evaluator{
+        &mut self.evaluator
+    }
+
+    /// The static evaluation of the current position, positive when white is better.
+    pub fn evaluate(&self,board:&Board)->Score{
+        self.evaluator.evaluate(&board.position)
+    }
+
+    /// The best move at full strength within the limits, None when the game is over. See
+    /// [`SearchLimits::new`] for the allowed ranges.
+    pub fn search(&mut self,board:&Board,limits:&SearchLimits)->Option<SearchResult>{
+        self.search_with(board,&limits.strength())
+    }
+
+    /// A move at one of the playing strengths of the webapp, None when the game is over.
+    pub fn play(&mut self,board:&Board,difficulty:Difficulty)->Option<SearchResult>{
+        self.search_with(board,&difficulty.strength())
+    }
+
+    /// A search with every setting spelled out, None when the game is over. Fewer expansions than
+    /// [`MIN_EXPANSIONS`] are raised to it.
+    pub fn search_with(&mut self,board:&Board,strength:&Strength)->Option<SearchResult>{
+        if board.is_game_over(){
+            return None;
+        }
+        let strength = Strength{expansions:strength.expansions.max(MIN_EXPANSIONS),..*strength};
+        let choice = bot::choose_move(board.position,&self.tablebase,&self.evaluator,&strength,&mut self.rng,None);
+        let next = board.position.play_move(&choice.mov);
+        Some(SearchResult{
+            best_move:Move::from_engine(&board.position,&choice.mov),
+            score:choice.score,
+            reason:choice.reason,
+            ponder:choice.reply.map(|reply| Move::from_engine(&next,&reply)),
+        })
+    }
+
+    /// A forced mate for the side to move in at most this many moves, the line alternates between
+    /// both sides and ends in mate.
+    pub fn find_mate(&self,board:&Board,moves:u32)->Option<Vec<Move>>{
+        let line = find_mate_within(&board.position,moves,u64::MAX)?;
+        let mut position = board.position;
+        Some(line.iter().map(|mov|{
+            let res = Move::from_engine(&position,mov);
+            position = position.play_move(mov);
+            res
+        }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn make_and_unmake(){
+        let mut board = Board::new();
+        let start = board.to_fen();
+        let mut fens = vec![start.clone()];
+        for name in ["e4","d5","exd5","Qxd5","Nc3","Qa5","d4","c6","Nf3","Bg4","Be2","e6","O-O"]{
+            let mov = board.parse_move(name).unwrap();
+            assert!(board.is_legal(mov));
+            board.make_move(mov).unwrap();
+            fens.push(board.to_fen());
+        }
+        assert_eq!(board.moves().count(),13);
+        assert_eq!(board.last_move().map(|mov| mov.to_string()),Some("e1g1".to_string()));
+        assert!(board.make_move(Move::new(Square::new(0,0).unwrap(),Square::new(0,7).unwrap(),None)).is_err());
+
+        while let Some(mov) = board.unmake_move(){
+            fens.pop();
+            assert_eq!(&board.to_fen(),fens.last().unwrap(),"{}",mov);
+        }
+        assert_eq!(board.to_fen(),start);
+        assert_eq!(board.legal_moves().count(),20);
+    }
+
+    #[test]
+    fn search(){
+        let mut engine = Engine::with_seed(3);
+        // the queen takes the undefended rook
+        let board = Board::from_fen("4k3/8/8/8/r7/8/8/Q3K3 w - - 0 1").unwrap();
+        let result = engine.search(&board,&SearchLimits::new(300,0,0)).unwrap();
+        assert_eq!(result.best_move.to_string(),"a1a4");
+
+        // too small limits still give a legal move
+        for expansions in [i32::MIN,0,1]{
+            let result = engine.search(&board,&SearchLimits::new(expansions,0,0)).unwrap();
+            assert!(board.is_legal(result.best_move));
+            let strength = Strength{expansions,..SearchLimits::new(2,0,0).strength()};
+            let result = engine.search_with(&board,&strength).unwrap();
+            assert!(board.is_legal(result.best_move));
+        }
+
+        let mate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(matches!(mate.status(),Status::Stalemate));
+        assert!(engine.search(&mate,&SearchLimits::new(100,0,0)).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+// Squares attacked by single pieces as bitboards, bit y*8+x with x=0 the h-file like the board.
+//
+// Unlike move generation this ignores pins, checks and whose turn it is, and it does not allocate:
+// it is what the evaluation asks about when it looks at which squares a piece controls. AttackMap holds
+// the result for a whole position, computed once per evaluation and shared by the terms that need it.
+
+use crate::*;
+
+const KNIGHT_STEPS:[(i8,i8);8] = [(1,2),(2,1),(2,-1),(1,-2),(-1,-2),(-2,-1),(-2,1),(-1,2)];
+const KING_STEPS:[(i8,i8);8] = [(1,0),(1,1),(0,1),(-1,1),(-1,0),(-1,-1),(0,-1),(1,-1)];
+const ROOK_DIRECTIONS:[(i8,i8);4] = [(1,0),(0,1),(-1,0),(0,-1)];
+const BISHOP_DIRECTIONS:[(i8,i8);4] = [(1,1),(-1,1),(-1,-1),(1,-1)];
+
+pub(crate) fn occupancy(board:&Board)->u64{
+    board.data.iter().enumerate().fold(0,|bits,(num,tile)| if matches!(tile,Tile::Empty) {bits} else {bits | 1 << num})
+}
+
+fn steps(pos:&Pos,steps:&[(i8,i8)])->u64{
+    steps.iter().filter_map(|(x,y)| pos.step(*x,*y).ok()).fold(0,|bits,to| bits | 1 << to.num)
+}
+
+// sliding until the edge or the first piece, which is attacked itself
+fn rays(pos:&Pos,directions:&[(i8,i8)],occupied:u64)->u64{
+    let mut bits = 0;
+    for (x,y) in directions{
+        let mut current = Pos::from_num(pos.num);
+        while let Ok(to) = current.step(*x,*y){
+            bits |= 1 << to.num;
+            if occupied & 1 << to.num != 0{
+                break;
+            }
+            current = to;
+        }
+    }
+    bits
+}
+
+// squares a piece of this colour on num attacks with the given squares occupied
+pub(crate) fn attacks(piece:Piece,color:Color,num:usize,occupied:u64)->u64{
+    let pos = Pos::from_num(num);
+    match piece{
+        Piece::Pawn=>{
+            let forward = if color == Color::White {1} else {-1};
+            steps(&pos,&[(1,forward),(-1,forward)])
+        }
+        Piece::Knight=>steps(&pos,&KNIGHT_STEPS),
+        Piece::King=>steps(&pos,&KING_STEPS),
+        Piece::Bishop=>rays(&pos,&BISHOP_DIRECTIONS,occupied),
+        Piece::Rook=>rays(&pos,&ROOK_DIRECTIONS,occupied),
+        Piece::Queen=>rays(&pos,&BISHOP_DIRECTIONS,occupied) | rays(&pos,&ROOK_DIRECTIONS,occupied),
+    }
+}
+
+// every piece's attacks in one position, with how many pieces of each colour attack every square
+#[derive(Clone, Debug)]
+pub struct AttackMap{
+    // squares attacked by the piece on each square, 0 for empty squares
+    pieces:[u64;64],
+    // per colour, white first
+    counts:[[u8;64];2],
+    attacked:[u64;2],
+    by_pawns:[u64;2],
+}
+
+impl AttackMap{
+
+    pub(crate) fn new(board:&Board)->AttackMap{
+        let occupied = occupancy(board);
+        let mut map = AttackMap{pieces:[0;64],counts:[[0;64];2],attacked:[0;2],by_pawns:[0;2]};
+        for (num,tile) in board.data.iter().enumerate(){
+            if let Tile::Taken(color,piece,_) = tile{
+                let bits = attacks(*piece,*color,num,occupied);
+                let c = color.to_num();
+                map.pieces[num] = bits;
+                map.attacked[c] |= bits;
+                if matches!(piece,Piece::Pawn){
+                    map.by_pawns[c] |= bits;
+                }
+                let mut rest = bits;
+                while rest != 0{
+                    map.counts[c][rest.trailing_zeros() as usize] += 1;
+                    rest &= rest - 1;
+                }
+            }
+        }
+        map
+    }
+
+    pub(crate) fn attacked_by_pawns(&self,color:Color)->u64{
+        self.by_pawns[color.to_num()]
+    }
+
+    pub(crate) fn count(&self,color:Color,num:usize)->u8{
+        self.counts[color.to_num()][num]
+    }
+
+    // squares the piece on num attacks
+    pub fn piece_attacks(&self,num:usize)->u64{
+        self.pieces[num]
+    }
+
+    // every square attacked by white or black
+    pub fn attacked(&self,white:bool)->u64{
+        self.attacked[if white {0} else {1}]
+    }
+
+    // how many white or black pieces attack the square
+    pub fn attackers(&self,num:usize,white:bool)->u8{
+        self.counts[if white {0} else {1}][num]
+    }
+}
+
+impl Board{
+
+    // attacked squares and attacker counts for both colours, pins and checks ignored
+    pub fn attack_map(&self)->AttackMap{
+        AttackMap::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::notation::parse_square;
+
+    fn bits(names:&[&str])->u64{
+        names.iter().fold(0,|bits,name| bits | 1 << parse_square(name).unwrap())
+    }
+
+    fn square(name:&str)->usize{
+        parse_square(name).unwrap()
+    }
+
+    #[test]
+    fn single_pieces(){
+        assert_eq!(attacks(Piece::Knight,Color::White,square("a1"),0),bits(&["b3","c2"]));
+        assert_eq!(attacks(Piece::Pawn,Color::White,square("e4"),0),bits(&["d5","f5"]));
+        assert_eq!(attacks(Piece::Pawn,Color::Black,square("e5"),0),bits(&["d4","f4"]));
+        assert_eq!(attacks(Piece::Pawn,Color::White,square("h2"),0),bits(&["g3"]));
+        assert_eq!(attacks(Piece::King,Color::Black,square("h8"),0),bits(&["g8","g7","h7"]));
+        // the blocker is attacked, the squares behind it are not
+        assert_eq!(attacks(Piece::Rook,Color::White,square("a1"),bits(&["a4","c1"])),bits(&["a2","a3","a4","b1","c1"]));
+        assert_eq!(attacks(Piece::Bishop,Color::White,square("c1"),bits(&["e3"])),bits(&["b2","a3","d2","e3"]));
+        assert_eq!(attacks(Piece::Queen,Color::White,square("d4"),0).count_ones(),27);
+    }
+
+    #[test]
+    fn map(){
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1").unwrap();
+        let map = board.attack_map();
+        assert_eq!(map.attacked_by_pawns(Color::White),bits(&["d5","f5"]));
+        assert_eq!(map.attacked_by_pawns(Color::Black),bits(&["c4","e4"]));
+        assert_eq!(map.piece_attacks(square("e1")),bits(&["d1","f1","d2","e2","f2"]));
+        assert_eq!(map.piece_attacks(square("e3")),0);
+        // the rook stops at the king
+        assert_eq!(map.piece_attacks(square("a1")),bits(&["a2","a3","a4","a5","a6","a7","a8","b1","c1","d1","e1"]));
+        assert_eq!(map.attackers(square("d1"),true),2);
+        assert_eq!(map.attackers(square("d5"),true),1);
+        assert_eq!(map.attackers(square("e4"),false),1);
+        assert_eq!(map.attackers(square("e4"),true),0);
+        assert!(map.attacked(false) & 1 << square("d7") != 0);
+        assert!(map.attacked(true) & 1 << square("h8") == 0);
+        assert_eq!(map.count(Color::White,square("a8")),1);
+    }
+}
@@ -0,0 +1,46 @@
+// Prints the evaluation of a position term by term
+// usage: eval [--params weights.json|weights.toml] [--network net.bin] [fen]
+
+use std::env;
+use std::path::Path;
+
+use chess_engine::{Board,EvalParams,Network};
+
+// removes "name value" from the arguments
+fn option(args:&mut Vec<String>,name:&str)->Option<String>{
+    let i = args.iter().position(|arg| arg == name)?;
+    if i + 1 >= args.len(){
+        eprintln!("{} needs a file",name);
+        std::process::exit(2);
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+fn main(){
+    let mut args:Vec<String> = env::args().skip(1).collect();
+    let params = match option(&mut args,"--params"){
+        Some(path)=>EvalParams::load(Path::new(&path)).unwrap_or_else(|e|{
+            eprintln!("invalid parameters: {}",e);
+            std::process::exit(2);
+        }),
+        None=>EvalParams::default(),
+    };
+    let network = option(&mut args,"--network").map(|path| Network::load(Path::new(&path)).unwrap_or_else(|e|{
+        eprintln!("invalid network: {}",e);
+        std::process::exit(2);
+    }));
+    let board = if args.is_empty(){
+        Board::new()
+    }else{
+        Board::from_fen(&args.join(" ")).unwrap_or_else(|e|{
+            eprintln!("invalid fen: {}",e);
+            std::process::exit(2);
+        })
+    };
+    println!("{}",board.eval_trace_with(&params));
+    if let Some(network) = network{
+        println!("network ({} hidden) {} for white",network.hidden_size(),network.evaluate(&board));
+    }
+}
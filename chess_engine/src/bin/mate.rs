@@ -0,0 +1,32 @@
+// Searches a forced mate for the side to move
+// usage: mate <moves> <fen>
+
+use std::env;
+
+use chess_engine::Board;
+
+fn main(){
+    let args:Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2{
+        eprintln!("usage: mate <moves> <fen>");
+        std::process::exit(2);
+    }
+    let moves:u32 = args[0].parse().unwrap_or_else(|_|{
+        eprintln!("expected a number of moves, got {}",args[0]);
+        std::process::exit(2);
+    });
+    let board = Board::from_fen(&args[1..].join(" ")).unwrap_or_else(|e|{
+        eprintln!("invalid fen: {}",e);
+        std::process::exit(2);
+    });
+
+    match board.find_mate(moves){
+        Some(line)=>{
+            println!("mate in {}: {}",line.len().div_ceil(2),board.line_to_coordinates(&line).join(" "));
+        }
+        None=>{
+            println!("no mate in {}",moves);
+            std::process::exit(1);
+        }
+    }
+}
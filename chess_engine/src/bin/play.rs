@@ -0,0 +1,348 @@
+// Plays against the bot in the terminal
+// usage: play [--black] [--level beginner|casual|intermediate|advanced|expert] [--syzygy dir]
+//
+// Moves are typed in SAN ("Nf3", "exd5", "O-O", "e8=Q") or coordinates ("g1f3"), "help" lists the
+// commands. Colours are left out when NO_COLOR is set.
+
+use std::env;
+use std::fs;
+use std::io::{self,BufRead,Write};
+use std::path::Path;
+
+use chess_engine::{choose_move,game_result,read_pgn,set_logger,write_pgn,Board,Difficulty,Evaluator,PossibleMove,Rng,SearchLimits,Tablebase};
+
+const HELP:&str = "\
+<move>          play a move in SAN (Nf3, exd5, O-O, e8=Q) or coordinates (g1f3)
+undo            take back your last move and the bot's answer
+hint            the bot's suggestion for you
+flip            turn the board around
+new [black]     start a new game, as white unless black is given
+level <name>    beginner, casual, intermediate, advanced or expert
+fen [<fen>]     show the position or set up a new one
+pgn             show the game
+load <file>     continue from a FEN or PGN file
+save <file>     write the game, as FEN if the name ends in .fen and as PGN otherwise
+quit            leave";
+
+// what the hint may spend
+const HINT_LIMITS:SearchLimits = SearchLimits{expansions:5000,mate_depth:2,time_ms:0};
+
+const LIGHT:&str = "48;5;180";
+const DARK:&str = "48;5;137";
+const LAST_LIGHT:&str = "48;5;186";
+const LAST_DARK:&str = "48;5;143";
+const CHECK:&str = "48;5;167";
+const WHITE_PIECE:&str = "1;38;5;231";
+const BLACK_PIECE:&str = "1;38;5;16";
+
+struct Session{
+    start:Board,
+    board:Board,
+    // every move played with the board it was played on
+    history:Vec<(Board,PossibleMove)>,
+    human_white:bool,
+    flipped:bool,
+    difficulty:Difficulty,
+    tablebase:Tablebase,
+    evaluator:Evaluator,
+    rng:Rng,
+    color:bool,
+}
+
+fn parse_difficulty(name:&str)->Option<Difficulty>{
+    match name.to_lowercase().as_str(){
+        "beginner"=>Some(Difficulty::Beginner),
+        "casual"=>Some(Difficulty::Casual),
+        "intermediate"=>Some(Difficulty::Intermediate),
+        "advanced"=>Some(Difficulty::Advanced),
+        "expert"=>Some(Difficulty::Expert),
+        _=>None,
+    }
+}
+
+// file 0 is the a-file, rank 0 the first rank
+fn parse_square(name:&str)->(usize,usize){
+    let bytes = name.as_bytes();
+    ((bytes[0] - b'a') as usize,(bytes[1] - b'1') as usize)
+}
+
+// the pieces as FEN letters, indexed by rank and file
+fn squares(board:&Board)->[[Option<char>;8];8]{
+    let mut res = [[None;8];8];
+    let placement = board.to_fen();
+    for (i,row) in placement.split(' ').next().unwrap_or("").split('/').enumerate(){
+        let mut file = 0;
+        for c in row.chars(){
+            match c.to_digit(10){
+                Some(empty)=>file += empty as usize,
+                None=>{
+                    if file < 8 && i < 8{
+                        res[7 - i][file] = Some(c);
+                    }
+                    file += 1;
+                }
+            }
+        }
+    }
+    res
+}
+
+fn glyph(piece:char,outlined:bool)->char{
+    let glyphs = if outlined {['♔','♕','♖','♗','♘','♙']} else {['♚','♛','♜','♝','♞','♟']};
+    match piece.to_ascii_lowercase(){
+        'k'=>glyphs[0],
+        'q'=>glyphs[1],
+        'r'=>glyphs[2],
+        'b'=>glyphs[3],
+        'n'=>glyphs[4],
+        _=>glyphs[5],
+    }
+}
+
+impl Session{
+
+    fn new(human_white:bool,difficulty:Difficulty,tablebase:Tablebase)->Session{
+        Session{
+            start:Board::new(),
+            board:Board::new(),
+            history:vec![],
+            human_white,
+            flipped:!human_white,
+            difficulty,
+            tablebase,
+            evaluator:Evaluator::default(),
+            rng:Rng::from_entropy(),
+            color:env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn set_game(&mut self,start:Board,moves:Vec<PossibleMove>){
+        self.start = start;
+        self.board = start;
+        self.history.clear();
+        for mov in moves{
+            let next = self.board.play_move(&mov);
+            self.history.push((self.board,mov));
+            self.board = next;
+        }
+    }
+
+    fn play(&mut self,mov:PossibleMove){
+        let next = self.board.play_move(&mov);
+        self.history.push((self.board,mov));
+        self.board = next;
+    }
+
+    fn over(&self)->bool{
+        game_result(&self.board) != "*"
+    }
+
+    fn human_to_move(&self)->bool{
+        self.board.white_to_move() == self.human_white
+    }
+
+    // "12. Nf3" or "12... Nf6"
+    fn last_move(&self)->Option<String>{
+        let (board,mov) = self.history.last()?;
+        let dots = if board.white_to_move() {"."} else {"..."};
+        Some(format!("{}{} {}",board.fullmove_number(),dots,board.san(mov)))
+    }
+
+    fn print_board(&self){
+        let pieces = squares(&self.board);
+        let last:Vec<(usize,usize)> = self.history.last().map(|(board,mov)|{
+            let name = mov.to_coordinate(board);
+            vec![parse_square(&name[0..2]),parse_square(&name[2..4])]
+        }).unwrap_or_default();
+        let king = if self.board.white_to_move() {'K'} else {'k'};
+        let checked = |rank:usize,file:usize| self.board.in_check() && pieces[rank][file] == Some(king);
+
+        let files:Vec<usize> = if self.flipped {(0..8).rev().collect()} else {(0..8).collect()};
+        let ranks:Vec<usize> = if self.flipped {(0..8).collect()} else {(0..8).rev().collect()};
+        let legend:String = files.iter().map(|file| format!(" {} ",(b'a' + *file as u8) as char)).collect();
+        println!();
+        for rank in ranks.iter().copied(){
+            let mut line = format!(" {} ",rank + 1);
+            for file in files.iter().copied(){
+                let piece = pieces[rank][file];
+                if !self.color{
+                    line += &format!(" {} ",piece.map_or('·',|piece| glyph(piece,piece.is_ascii_uppercase())));
+                    continue;
+                }
+                let light = (rank + file) % 2 == 1;
+                let background = if checked(rank,file){
+                    CHECK
+                }else if last.contains(&(file,rank)){
+                    if light {LAST_LIGHT} else {LAST_DARK}
+                }else if light {LIGHT} else {DARK};
+                let foreground = if piece.is_some_and(|piece| piece.is_ascii_uppercase()) {WHITE_PIECE} else {BLACK_PIECE};
+                line += &format!("\x1b[{};{}m {} \x1b[0m",background,foreground,piece.map_or(' ',|piece| glyph(piece,false)));
+            }
+            println!("{}",line);
+        }
+        println!("   {}",legend);
+        println!();
+
+        if let Some(last) = self.last_move(){
+            println!("Last move: {}",last);
+        }
+        match game_result(&self.board){
+            "*" if self.board.in_check()=>println!("Check!"),
+            "*"=>{}
+            "1/2-1/2"=>println!("Stalemate, 1/2-1/2"),
+            result=>println!("Checkmate, {}",result),
+        }
+    }
+
+    fn bot_move(&mut self){
+        let choice = choose_move(self.board,&self.tablebase,&self.evaluator,&self.difficulty.strength(),&mut self.rng,None);
+        self.play(choice.mov);
+    }
+
+    fn hint(&mut self){
+        if self.over(){
+            println!("The game is over.");
+            return;
+        }
+        let choice = choose_move(self.board,&self.tablebase,&self.evaluator,&HINT_LIMITS.strength(),&mut self.rng,None);
+        println!("Try {} (score {}).",self.board.san(&choice.mov),choice.score);
+    }
+
+    // back to the human's previous turn
+    fn undo(&mut self){
+        let mut undone = false;
+        while let Some((board,_)) = self.history.pop(){
+            self.board = board;
+            undone = true;
+            if self.human_to_move(){
+                break;
+            }
+        }
+        if !undone{
+            println!("Nothing to undo.");
+        }
+    }
+
+    fn pgn(&self)->String{
+        let (white,black) = if self.human_white {("You","Steinfish Rochade")} else {("Steinfish Rochade","You")};
+        let moves:Vec<PossibleMove> = self.history.iter().map(|(_,mov)| mov.clone()).collect();
+        write_pgn(&self.start,&moves,&[("Event","Terminal game"),("White",white),("Black",black)])
+    }
+
+    fn load(&mut self,path:&str)->Result<(),String>{
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}",path,e))?;
+        match Board::from_fen(text.trim()){
+            Ok(board)=>self.set_game(board,vec![]),
+            Err(_)=>{
+                let (start,moves) = read_pgn(&text)?;
+                self.set_game(start,moves);
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self,path:&str)->Result<(),String>{
+        let text = if path.ends_with(".fen") {self.board.to_fen() + "\n"} else {self.pgn()};
+        fs::write(path,text).map_err(|e| format!("{}: {}",path,e))
+    }
+
+    // false once the human wants to leave
+    fn command(&mut self,line:&str)->bool{
+        let (command,argument) = line.split_once(' ').map(|(command,argument)| (command,argument.trim())).unwrap_or((line,""));
+        match command{
+            "quit"|"exit"=>return false,
+            "help"=>println!("{}",HELP),
+            "undo"=>self.undo(),
+            "hint"=>self.hint(),
+            "flip"=>self.flipped = !self.flipped,
+            "new"=>{
+                self.human_white = argument != "black";
+                self.flipped = !self.human_white;
+                self.set_game(Board::new(),vec![]);
+            }
+            "level"=>match parse_difficulty(argument){
+                Some(difficulty)=>self.difficulty = difficulty,
+                None=>println!("Unknown level {}, try beginner, casual, intermediate, advanced or expert.",argument),
+            },
+            "fen" if argument.is_empty()=>println!("{}",self.board.to_fen()),
+            "fen"=>match Board::from_fen(argument){
+                Ok(board)=>self.set_game(board,vec![]),
+                Err(e)=>println!("Invalid FEN: {}",e),
+            },
+            "pgn"=>print!("{}",self.pgn()),
+            "load"=>if let Err(e) = self.load(argument){
+                println!("Could not load the game: {}",e);
+            },
+            "save"=>match self.save(argument){
+                Ok(())=>println!("Saved to {}.",argument),
+                Err(e)=>println!("Could not save the game: {}",e),
+            },
+            _ if self.over()=>println!("The game is over, type new for another one."),
+            _ if !self.human_to_move()=>println!("It is the bot's turn."),
+            _=>match self.board.parse_move(line){
+                Ok(mov)=>self.play(mov),
+                Err(e)=>println!("{}, type help for the commands.",e),
+            },
+        }
+        true
+    }
+}
+
+// removes "name value" from the arguments
+fn option(args:&mut Vec<String>,name:&str)->Option<String>{
+    let i = args.iter().position(|arg| arg == name)?;
+    if i + 1 >= args.len(){
+        eprintln!("{} needs a value",name);
+        std::process::exit(2);
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+fn main(){
+    // the engine's diagnostics would run through the board
+    set_logger(|_| {});
+    let mut args:Vec<String> = env::args().skip(1).collect();
+    let difficulty = option(&mut args,"--level").map_or(Difficulty::Intermediate,|name| parse_difficulty(&name).unwrap_or_else(||{
+        eprintln!("unknown level {}",name);
+        std::process::exit(2);
+    }));
+    let mut tablebase = Tablebase::new();
+    if let Some(path) = option(&mut args,"--syzygy"){
+        if let Err(e) = tablebase.add_directory(Path::new(&path)){
+            eprintln!("{}: {}",path,e);
+            std::process::exit(2);
+        }
+    }
+    let human_white = !args.iter().any(|arg| arg == "--black");
+
+    let mut session = Session::new(human_white,difficulty,tablebase);
+    println!("Type a move like e4 or Nf3, or help for the commands.");
+    let mut lines = io::stdin().lock().lines().map_while(Result::ok);
+    let mut shown = false;
+    loop{
+        if !session.over() && !session.human_to_move(){
+            session.bot_move();
+            shown = false;
+        }
+        if !shown{
+            session.print_board();
+        }
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let Some(line) = lines.next() else{
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty(){
+            shown = true;
+            continue;
+        }
+        let before = (session.board.to_fen(),session.flipped,session.history.len());
+        if !session.command(line){
+            break;
+        }
+        shown = before == (session.board.to_fen(),session.flipped,session.history.len());
+    }
+}
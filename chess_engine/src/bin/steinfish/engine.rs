@@ -0,0 +1,225 @@
+// What both protocols share: the options, the limits of one search and a search running on its own
+// thread, so commands like stop can still be read while it thinks.
+//
+// The search is a tree search without a fixed depth: a depth limit stops it once the principal
+// variation is that many plies long, a node limit after that many expansions.
+
+use std::panic::{self,AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool,Ordering};
+use std::sync::{Arc,Mutex,MutexGuard,PoisonError};
+use std::thread::{self,JoinHandle};
+use std::time::{Duration,Instant};
+
+use chess_engine::{choose_move_with,log,Board,EvalParams,Evaluator,Network,PossibleMove,Rng,Score,SearchLimits,Tablebase,MIN_EXPANSIONS};
+
+// expansions for a search without any limit
+const DEFAULT_EXPANSIONS:i32 = 20000;
+pub const DEFAULT_MATE_DEPTH:u32 = 3;
+pub const DEFAULT_QUIESCENCE_DEPTH:u32 = 4;
+// kept back from the clock for the GUI and the operating system
+const MOVE_OVERHEAD_MS:u64 = 30;
+// moves the remaining time is spread over when the GUI does not say
+const MOVES_TO_GO:u64 = 30;
+const REPORT_INTERVAL_MS:u128 = 1000;
+
+// what the options change, every search reads it
+pub struct Engine{
+    tablebase:Tablebase,
+    evaluator:Evaluator,
+    mate_depth:u32,
+    quiescence_depth:u32,
+}
+
+impl Engine{
+
+    pub fn new()->Engine{
+        Engine{
+            tablebase:Tablebase::new(),
+            evaluator:Evaluator::default(),
+            mate_depth:DEFAULT_MATE_DEPTH,
+            quiescence_depth:DEFAULT_QUIESCENCE_DEPTH,
+        }
+    }
+
+    // names are compared ignoring case, an empty file name goes back to the default
+    pub fn set_option(&mut self,name:&str,value:&str)->Result<(),String>{
+        let value = if value == "<empty>" {""} else {value};
+        match name.to_lowercase().as_str(){
+            "syzygypath"=>std::env::split_paths(value).filter(|path| !path.as_os_str().is_empty()).try_for_each(|path|{
+                self.tablebase.add_directory(&path).map(|_|()).map_err(|e| format!("{}: {}",path.display(),e))
+            }),
+            "evalfile" if value.is_empty()=>{
+                self.evaluator.set_network(None);
+                Ok(())
+            }
+            "evalfile"=>Network::load(Path::new(value)).map(|network| self.evaluator.set_network(Some(network))),
+            "evalparams" if value.is_empty()=>{
+                self.evaluator.set_params(EvalParams::default());
+                Ok(())
+            }
+            "evalparams"=>EvalParams::load(Path::new(value)).map(|params| self.evaluator.set_params(params)),
+            "matedepth"=>value.parse().map(|depth| self.mate_depth = depth).map_err(|_| format!("expected a number, got {}",value)),
+            "quiescencedepth"=>value.parse().map(|depth| self.quiescence_depth = depth).map_err(|_| format!("expected a number, got {}",value)),
+            _=>Err(format!("unknown option {}",name)),
+        }
+    }
+}
+
+// the limits of one search, none at all searches the default number of expansions
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits{
+    pub expansions:Option<i32>,
+    pub time_ms:Option<u64>,
+    pub depth:Option<usize>,
+    // only stops when told to
+    pub infinite:bool,
+}
+
+impl Limits{
+
+    // time for one move from the clock: an even share plus most of the increment, never more than half
+    // of what is left
+    pub fn clock(time_ms:u64,increment_ms:u64,moves_to_go:Option<u64>)->u64{
+        let share = (time_ms / moves_to_go.unwrap_or(MOVES_TO_GO).max(1) + increment_ms * 3 / 4).min(time_ms / 2);
+        share.saturating_sub(MOVE_OVERHEAD_MS).max(1)
+    }
+}
+
+// how far a search got
+#[derive(Clone, Debug)]
+pub struct Thinking{
+    pub nodes:i32,
+    pub elapsed:Duration,
+    // for the side to move
+    pub score:Score,
+    // starts with the best move
+    pub line:Vec<PossibleMove>,
+    // why the search failed, the move is then only the first legal one
+    pub error:Option<String>,
+}
+
+impl Thinking{
+
+    pub fn depth(&self)->usize{
+        self.line.len().max(1)
+    }
+}
+
+// searches on the calling thread, report sees the search about once a second, None without a legal move
+pub fn think(engine:&Engine,board:Board,limits:Limits,stop:&AtomicBool,report:&mut dyn FnMut(&Thinking))->Option<Thinking>{
+    let start = Instant::now();
+    if board.legal_moves().is_empty(){
+        return None;
+    }
+    let unlimited = limits.infinite || limits.depth.is_some() || limits.time_ms.is_some();
+    let expansions = limits.expansions.map_or(if unlimited {i32::MAX} else {DEFAULT_EXPANSIONS},|nodes| nodes.max(MIN_EXPANSIONS));
+    let time_ms = if limits.infinite {0} else {limits.time_ms.unwrap_or(0).min(u32::MAX as u64) as u32};
+    let mut strength = SearchLimits::new(expansions,engine.mate_depth,time_ms).strength();
+    strength.quiescence_depth = engine.quiescence_depth;
+
+    let mut last_report = 0;
+    let mut nodes = 0;
+    let mut pv = vec![];
+    let choice = choose_move_with(board,&engine.tablebase,&engine.evaluator,&strength,&mut Rng::from_entropy(),None,&mut |search|{
+        pv = search.principal_variation();
+        nodes = search.expansions();
+        if start.elapsed().as_millis() >= last_report + REPORT_INTERVAL_MS{
+            last_report = start.elapsed().as_millis();
+            report(&Thinking{nodes,elapsed:start.elapsed(),score:search.score(),line:pv.clone(),error:None});
+        }
+        !stop.load(Ordering::Relaxed) && limits.depth.is_none_or(|depth| pv.len() < depth)
+    });
+
+    // the last principal variation unless the move came from somewhere else or was picked among equals
+    let line = match pv.first(){
+        Some(first) if first.to_coordinate(&board) == choice.mov.to_coordinate(&board)=>pv,
+        _=>[Some(choice.mov),choice.reply].into_iter().flatten().collect(),
+    };
+    // an infinite search only ends when stopped, even when the answer was found at once
+    while limits.infinite && !stop.load(Ordering::Relaxed){
+        thread::sleep(Duration::from_millis(5));
+    }
+    Some(Thinking{nodes,elapsed:start.elapsed(),score:choice.score,line,error:None})
+}
+
+// think, but a panicking search still answers with a legal move
+fn think_or_fall_back(engine:&Engine,board:Board,limits:Limits,stop:&AtomicBool,report:&mut dyn FnMut(&Thinking))->Option<Thinking>{
+    let start = Instant::now();
+    panic::catch_unwind(AssertUnwindSafe(|| think(engine,board,limits,stop,report))).unwrap_or_else(|payload|{
+        let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".to_string());
+        let mov = board.legal_moves().into_iter().next()?;
+        Some(Thinking{nodes:0,elapsed:start.elapsed(),score:Score::ZERO,line:vec![mov],error:Some(format!("search failed: {}",message))})
+    })
+}
+
+// runs one search at a time on another thread
+pub struct Searcher{
+    engine:Arc<Mutex<Engine>>,
+    stop:Arc<AtomicBool>,
+    running:Option<JoinHandle<()>>,
+}
+
+impl Searcher{
+
+    pub fn new()->Searcher{
+        Searcher{engine:Arc::new(Mutex::new(Engine::new())),stop:Arc::new(AtomicBool::new(false)),running:None}
+    }
+
+    // stops a running search first, done gets its result on the search thread
+    pub fn start<R,D>(&mut self,board:Board,limits:Limits,mut report:R,done:D)
+    where R:FnMut(&Thinking) + Send + 'static,D:FnOnce(Option<Thinking>) + Send + 'static{
+        self.stop();
+        self.stop.store(false,Ordering::Relaxed);
+        let (engine,stop) = (self.engine.clone(),self.stop.clone());
+        self.running = Some(thread::spawn(move ||{
+            // a search that panicked before left the lock poisoned, the engine itself is still fine
+            let engine = engine.lock().unwrap_or_else(PoisonError::into_inner);
+            let thinking = think_or_fall_back(&engine,board,limits,&stop,&mut report);
+            done(thinking);
+        }));
+    }
+
+    // a running search ends with the best move so far, this waits for it
+    pub fn stop(&mut self){
+        if let Some(running) = self.running.take(){
+            self.stop.store(true,Ordering::Relaxed);
+            if running.join().is_err(){
+                log("the search thread ended with a panic");
+            }
+        }
+    }
+
+    // stops a running search to change the options
+    pub fn engine(&mut self)->MutexGuard<'_,Engine>{
+        self.stop();
+        self.engine.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // node limits below what a search needs are raised instead of failing on the search thread
+    #[test]
+    fn tiny_node_limits(){
+        let engine = Engine::new();
+        let board = Board::new();
+        for nodes in [0,1]{
+            let limits = Limits{expansions:Some(nodes),..Limits::default()};
+            let thinking = think_or_fall_back(&engine,board,limits,&AtomicBool::new(false),&mut |_| {}).unwrap();
+            assert!(thinking.error.is_none());
+            assert!(board.play_coordinate(&board.line_to_coordinates(&thinking.line)[0]).is_ok());
+        }
+
+        let mut searcher = Searcher::new();
+        let (sender,receiver) = std::sync::mpsc::channel();
+        searcher.start(board,Limits{expansions:Some(1),..Limits::default()},|_| {},move |thinking| sender.send(thinking.is_some()).unwrap());
+        searcher.stop();
+        assert_eq!(receiver.recv(),Ok(true));
+        assert!(searcher.engine().set_option("MateDepth","2").is_ok());
+    }
+}
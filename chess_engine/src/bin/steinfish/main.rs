@@ -0,0 +1,22 @@
+// The engine for chess GUIs and tournament tools, speaking UCI or the xboard protocol (CECP)
+// usage: steinfish
+//
+// The first command picks the protocol: "xboard" for CECP, anything else is read as UCI.
+
+mod engine;
+mod uci;
+mod xboard;
+
+use std::io::{self,BufRead};
+
+fn main(){
+    let mut lines = io::stdin().lock().lines().map_while(Result::ok);
+    let Some(first) = lines.next() else{
+        return;
+    };
+    if first.trim() == "xboard"{
+        xboard::run(lines);
+    }else{
+        uci::run(std::iter::once(first).chain(lines));
+    }
+}
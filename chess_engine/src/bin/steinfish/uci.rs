@@ -0,0 +1,176 @@
+// The UCI protocol: the GUI keeps the game and sends the whole position before every go.
+
+use chess_engine::{Board,Score};
+
+use crate::engine::{self,Limits,Searcher,Thinking};
+
+fn number<T:std::str::FromStr>(tokens:&[&str],i:usize)->Option<T>{
+    tokens.get(i + 1).and_then(|token| token.parse().ok())
+}
+
+fn parse_go(tokens:&[&str],white:bool)->Limits{
+    let mut limits = Limits::default();
+    let (mut time,mut increment,mut moves_to_go) = (None,0,None);
+    for (i,token) in tokens.iter().enumerate(){
+        match *token{
+            "wtime" if white=>time = number(tokens,i),
+            "btime" if !white=>time = number(tokens,i),
+            "winc" if white=>increment = number(tokens,i).unwrap_or(0),
+            "binc" if !white=>increment = number(tokens,i).unwrap_or(0),
+            "movestogo"=>moves_to_go = number(tokens,i),
+            "movetime"=>limits.time_ms = number(tokens,i),
+            "depth"=>limits.depth = number(tokens,i),
+            "nodes"=>limits.expansions = number(tokens,i),
+            "infinite"=>limits.infinite = true,
+            _=>{}
+        }
+    }
+    if limits.time_ms.is_none(){
+        limits.time_ms = time.map(|time| Limits::clock(time,increment,moves_to_go));
+    }
+    limits
+}
+
+// "startpos" or "fen <fields>", then optionally "moves <coordinate moves>"
+fn parse_position(tokens:&[&str])->Result<Board,String>{
+    let moves_at = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let mut board = match tokens.first(){
+        Some(&"startpos")=>Board::new(),
+        Some(&"fen")=>Board::from_fen(&tokens[1..moves_at].join(" "))?,
+        _=>return Err("expected startpos or fen".to_string()),
+    };
+    for name in tokens.iter().skip(moves_at + 1){
+        board = board.play_coordinate(name)?;
+    }
+    Ok(board)
+}
+
+fn score(score:Score)->String{
+    match score.mate_moves(){
+        Some(moves)=>format!("mate {}",moves),
+        None=>format!("cp {}",score.raw()),
+    }
+}
+
+fn info(board:&Board,thinking:&Thinking){
+    let ms = thinking.elapsed.as_millis();
+    let nps = thinking.nodes as u128 * 1000 / ms.max(1);
+    let mut res = format!("info depth {} nodes {} time {} nps {} score {}",thinking.depth(),thinking.nodes,ms,nps,score(thinking.score));
+    if !thinking.line.is_empty(){
+        res += &format!(" pv {}",board.line_to_coordinates(&thinking.line).join(" "));
+    }
+    println!("{}",res);
+}
+
+pub fn run(lines:impl Iterator<Item=String>){
+    let mut searcher = Searcher::new();
+    let mut board = Board::new();
+
+    for line in lines{
+        let tokens:Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied(){
+            Some("uci")=>{
+                println!("id name Steinfish Rochade");
+                println!("id author the Steinfish Rochade authors");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name EvalFile type string default <empty>");
+                println!("option name EvalParams type string default <empty>");
+                println!("option name MateDepth type spin default {} min 0 max 8",engine::DEFAULT_MATE_DEPTH);
+                println!("option name QuiescenceDepth type spin default {} min 0 max 16",engine::DEFAULT_QUIESCENCE_DEPTH);
+                println!("uciok");
+            }
+            Some("isready")=>println!("readyok"),
+            Some("ucinewgame")=>{
+                searcher.stop();
+                board = Board::new();
+            }
+            Some("position")=>{
+                searcher.stop();
+                match parse_position(&tokens[1..]){
+                    Ok(position)=>board = position,
+                    Err(e)=>println!("info string {}",e),
+                }
+            }
+            Some("go")=>{
+                let limits = parse_go(&tokens[1..],board.white_to_move());
+                searcher.start(board,limits,move |thinking| info(&board,thinking),move |thinking|{
+                    let Some(thinking) = thinking else{
+                        println!("bestmove 0000");
+                        return;
+                    };
+                    if let Some(error) = &thinking.error{
+                        println!("info string {}",error);
+                    }
+                    info(&board,&thinking);
+                    let names = board.line_to_coordinates(&thinking.line);
+                    match names.get(1){
+                        Some(reply)=>println!("bestmove {} ponder {}",names[0],reply),
+                        None=>println!("bestmove {}",names[0]),
+                    }
+                });
+            }
+            Some("stop")=>searcher.stop(),
+            Some("setoption")=>{
+                // setoption name <name with spaces> value <value with spaces>
+                let value_at = tokens.iter().position(|token| *token == "value").unwrap_or(tokens.len());
+                let name = tokens.get(2..value_at).unwrap_or(&[]).join(" ");
+                let value = tokens.get(value_at + 1..).unwrap_or(&[]).join(" ");
+                if let Err(e) = searcher.engine().set_option(&name,&value){
+                    println!("info string {}",e);
+                }
+            }
+            Some("quit")=>break,
+            // anything else is ignored, as the protocol asks
+            _=>{}
+        }
+    }
+    searcher.stop();
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn position(command:&str)->Result<String,String>{
+        let tokens:Vec<&str> = command.split_whitespace().collect();
+        parse_position(&tokens).map(|board| board.to_fen())
+    }
+
+    fn go(command:&str,white:bool)->Limits{
+        let tokens:Vec<&str> = command.split_whitespace().collect();
+        parse_go(&tokens,white)
+    }
+
+    #[test]
+    fn positions(){
+        assert_eq!(position("startpos").unwrap(),Board::start_fen());
+        assert_eq!(position("startpos moves e2e4 e7e5 g1f3").unwrap(),"rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 0 2");
+        assert_eq!(position("fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap(),"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(position("fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7").unwrap(),"8/3k4/8/8/4P3/8/8/4K3 w - - 0 2");
+        assert_eq!(position("fen 4k3/6P1/8/8/8/8/8/4K3 w - - 0 1 moves g7g8n").unwrap(),"4k1N1/8/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(position("startpos moves e2e5").is_err());
+        assert!(position("fen 4k3/8 w - - 0 1").is_err());
+        assert!(position("moves e2e4").is_err());
+    }
+
+    #[test]
+    fn clock(){
+        // an even share of the remaining moves, less the overhead
+        let limits = go("wtime 60000 btime 30000 movestogo 20",true);
+        assert_eq!(limits.time_ms,Some(2970));
+        let limits = go("wtime 60000 btime 30000 movestogo 20",false);
+        assert_eq!(limits.time_ms,Some(1470));
+        // 30 moves without movestogo, and most of the increment
+        let limits = go("wtime 9000 btime 9000 winc 1000 binc 1000",true);
+        assert_eq!(limits.time_ms,Some(300 + 750 - 30));
+        // never more than half the clock
+        let limits = go("btime 1000 binc 5000",false);
+        assert_eq!(limits.time_ms,Some(470));
+
+        let limits = go("wtime 60000 movetime 500",true);
+        assert_eq!(limits.time_ms,Some(500));
+        let limits = go("depth 5 nodes 1",true);
+        assert_eq!((limits.depth,limits.expansions,limits.time_ms,limits.infinite),(Some(5),Some(1),None,false));
+        assert!(go("infinite",true).infinite);
+    }
+}
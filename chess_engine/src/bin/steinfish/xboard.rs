@@ -0,0 +1,310 @@
+// The xboard protocol (CECP): the engine keeps the game itself, the GUI sends the moves one at a time and
+// tells the engine when to play.
+
+use std::sync::{Arc,Mutex};
+
+use chess_engine::{Board,Score};
+
+use crate::engine::{self,Limits,Searcher,Thinking};
+
+// what the commands change, shared with the search thread that plays the engine's move
+struct Session{
+    board:Board,
+    // the positions before every move played, for undo
+    history:Vec<Board>,
+    // the engine only plays when told to
+    force:bool,
+    engine_white:bool,
+    post:bool,
+    // moves per time control (0 for the whole game) and increment from level
+    moves_per_control:u64,
+    increment_ms:u64,
+    // the engine's clock from the last time command
+    time_ms:Option<u64>,
+    // st and sd
+    move_time_ms:Option<u64>,
+    depth:Option<usize>,
+    // a search started before this changed is thrown away
+    generation:u64,
+}
+
+impl Session{
+
+    fn new()->Session{
+        Session{
+            board:Board::new(),
+            history:vec![],
+            force:false,
+            engine_white:false,
+            post:false,
+            moves_per_control:0,
+            increment_ms:0,
+            time_ms:None,
+            move_time_ms:None,
+            depth:None,
+            generation:0,
+        }
+    }
+
+    fn play(&mut self,board:Board){
+        self.history.push(self.board);
+        self.board = board;
+    }
+
+    fn limits(&self)->Limits{
+        let mut limits = Limits{depth:self.depth,time_ms:self.move_time_ms,..Limits::default()};
+        if limits.time_ms.is_none(){
+            let moves_to_go = (self.moves_per_control > 0).then(|| self.moves_per_control - (self.board.fullmove_number() as u64 - 1) % self.moves_per_control);
+            limits.time_ms = self.time_ms.map(|time| Limits::clock(time,self.increment_ms,moves_to_go));
+        }
+        limits
+    }
+}
+
+// mates as 100000 plus the moves to mate, as the protocol asks
+fn score(score:Score)->i32{
+    match score.mate_moves(){
+        Some(moves)=>moves.signum() * 100000 + moves,
+        None=>score.raw(),
+    }
+}
+
+fn post(board:&Board,thinking:&Thinking){
+    println!("{} {} {} {} {}",thinking.depth(),score(thinking.score),thinking.elapsed.as_millis() / 10,thinking.nodes,board.line_to_coordinates(&thinking.line).join(" "));
+}
+
+// prints the result when the game is over
+fn game_over(board:&Board)->bool{
+    if !board.legal_moves().is_empty(){
+        return false;
+    }
+    if !board.in_check(){
+        println!("1/2-1/2 {{Stalemate}}");
+    }else if board.white_to_move(){
+        println!("0-1 {{Black mates}}");
+    }else{
+        println!("1-0 {{White mates}}");
+    }
+    true
+}
+
+// minutes or minutes:seconds
+fn parse_base(base:&str)->Option<u64>{
+    let (minutes,seconds) = base.split_once(':').unwrap_or((base,"0"));
+    Some(minutes.parse::<u64>().ok()? * 60000 + seconds.parse::<u64>().ok()? * 1000)
+}
+
+// starts the engine's move when it is its turn
+fn think(session:&Arc<Mutex<Session>>,searcher:&mut Searcher){
+    let (board,limits,posting,generation) = {
+        let session = session.lock().unwrap();
+        if session.force || session.board.white_to_move() != session.engine_white || session.board.legal_moves().is_empty(){
+            return;
+        }
+        (session.board,session.limits(),session.post,session.generation)
+    };
+    let shared = session.clone();
+    searcher.start(board,limits,move |thinking| if posting {post(&board,thinking)},move |thinking|{
+        let Some(thinking) = thinking else{
+            return;
+        };
+        let mut session = shared.lock().unwrap();
+        if session.generation != generation{
+            return;
+        }
+        if let Some(error) = &thinking.error{
+            println!("tellusererror {}",error);
+        }
+        if posting{
+            post(&board,&thinking);
+        }
+        let name = board.line_to_coordinates(&thinking.line[..1]).remove(0);
+        session.play(board.play_coordinate(&name).unwrap());
+        println!("move {}",name);
+        game_over(&session.board);
+    });
+}
+
+// stops thinking without playing the move
+fn abort(session:&Arc<Mutex<Session>>,searcher:&mut Searcher){
+    session.lock().unwrap().generation += 1;
+    searcher.stop();
+}
+
+// handles one line from the GUI, false once it quits
+fn command(session:&Arc<Mutex<Session>>,searcher:&mut Searcher,line:&str)->bool{
+    let tokens:Vec<&str> = line.split_whitespace().collect();
+    let Some(&command) = tokens.first() else{
+        return true;
+    };
+    let argument = tokens.get(1).copied().unwrap_or("");
+
+    // commands that may come while the engine thinks and leave the search alone
+    match command{
+        "?"=>{
+            searcher.stop();
+            return true;
+        }
+        "ping"=>{
+            println!("pong {}",argument);
+            return true;
+        }
+        "time"=>{
+            session.lock().unwrap().time_ms = argument.parse::<u64>().ok().map(|centiseconds| centiseconds * 10);
+            return true;
+        }
+        "post"|"nopost"=>{
+            session.lock().unwrap().post = command == "post";
+            return true;
+        }
+        "xboard"|"otim"|"accepted"|"rejected"|"hard"|"easy"|"random"|"computer"|"name"|"rating"|"ics"|"draw"|"hint"|"bk"|"cores"|"memory"=>return true,
+        _=>{}
+    }
+
+    abort(session,searcher);
+    match command{
+        "protover"=>{
+            println!("feature myname=\"Steinfish Rochade\" setboard=1 usermove=1 ping=1 playother=1 colors=0 analyze=0 sigint=0 sigterm=0 reuse=1 done=0");
+            println!("feature option=\"SyzygyPath -path \"");
+            println!("feature option=\"EvalFile -file \"");
+            println!("feature option=\"EvalParams -file \"");
+            println!("feature option=\"MateDepth -spin {} 0 8\"",engine::DEFAULT_MATE_DEPTH);
+            println!("feature option=\"QuiescenceDepth -spin {} 0 16\"",engine::DEFAULT_QUIESCENCE_DEPTH);
+            println!("feature done=1");
+        }
+        "new"=>{
+            let mut session = session.lock().unwrap();
+            let (moves_per_control,increment_ms,post,generation) = (session.moves_per_control,session.increment_ms,session.post,session.generation);
+            *session = Session{moves_per_control,increment_ms,post,generation,..Session::new()};
+        }
+        "force"=>session.lock().unwrap().force = true,
+        "go"=>{
+            {
+                let mut session = session.lock().unwrap();
+                session.force = false;
+                session.engine_white = session.board.white_to_move();
+            }
+            think(session,searcher);
+        }
+        "playother"=>{
+            let mut session = session.lock().unwrap();
+            session.force = false;
+            session.engine_white = !session.board.white_to_move();
+        }
+        "setboard"=>{
+            let mut session = session.lock().unwrap();
+            match Board::from_fen(&tokens[1..].join(" ")){
+                Ok(board)=>{
+                    session.board = board;
+                    session.history.clear();
+                }
+                Err(e)=>println!("tellusererror Illegal position: {}",e),
+            }
+        }
+        "undo"|"remove"=>{
+            let mut session = session.lock().unwrap();
+            for _ in 0..if command == "undo" {1} else {2}{
+                if let Some(board) = session.history.pop(){
+                    session.board = board;
+                }
+            }
+        }
+        "level"=>{
+            let mut session = session.lock().unwrap();
+            session.moves_per_control = argument.parse().unwrap_or(0);
+            session.time_ms = tokens.get(2).and_then(|base| parse_base(base));
+            session.increment_ms = tokens.get(3).and_then(|increment| increment.parse::<f64>().ok()).map_or(0,|seconds| (seconds * 1000.) as u64);
+            session.move_time_ms = None;
+        }
+        "st"=>session.lock().unwrap().move_time_ms = argument.parse::<f64>().ok().map(|seconds| (seconds * 1000.) as u64),
+        "sd"=>session.lock().unwrap().depth = argument.parse().ok(),
+        "option"=>{
+            let (name,value) = tokens[1..].join(" ").split_once('=').map(|(name,value)| (name.to_string(),value.to_string())).unwrap_or((tokens[1..].join(" "),String::new()));
+            if let Err(e) = searcher.engine().set_option(&name,&value){
+                println!("Error (bad option): {}",e);
+            }
+        }
+        "result"=>session.lock().unwrap().force = true,
+        "quit"=>return false,
+        _=>{
+            // without usermove=1 moves come bare
+            let name = if command == "usermove" {argument} else {command};
+            let played = {
+                let mut session = session.lock().unwrap();
+                match session.board.play_coordinate(name){
+                    Ok(board)=>{
+                        session.play(board);
+                        !game_over(&session.board)
+                    }
+                    Err(_) if command == "usermove"=>{
+                        println!("Illegal move: {}",name);
+                        false
+                    }
+                    Err(_)=>{
+                        println!("Error (unknown command): {}",command);
+                        false
+                    }
+                }
+            };
+            if played{
+                think(session,searcher);
+            }
+        }
+    }
+    true
+}
+
+pub fn run(lines:impl Iterator<Item=String>){
+    let session = Arc::new(Mutex::new(Session::new()));
+    let mut searcher = Searcher::new();
+    for line in lines{
+        if !command(&session,&mut searcher,&line){
+            break;
+        }
+    }
+    abort(&session,&mut searcher);
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn send(session:&Arc<Mutex<Session>>,searcher:&mut Searcher,lines:&[&str]){
+        for line in lines{
+            assert!(command(session,searcher,line),"{}",line);
+        }
+    }
+
+    #[test]
+    fn clock_and_moves(){
+        let session = Arc::new(Mutex::new(Session::new()));
+        let mut searcher = Searcher::new();
+        send(&session,&mut searcher,&["new","force","level 40 5 2","time 30000","usermove e2e4","usermove e7e5","usermove e1e3","undo"]);
+        {
+            let session = session.lock().unwrap();
+            assert_eq!((session.moves_per_control,session.increment_ms,session.time_ms),(40,2000,Some(300000)));
+            assert_eq!(session.history.len(),1);
+            assert_eq!(session.board.to_fen(),"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+            // 40 moves to go: a 40th of five minutes and most of the increment
+            assert_eq!(session.limits().time_ms,Some(7500 + 1500 - 30));
+        }
+
+        send(&session,&mut searcher,&["level 0 2:30 0","time 15000","st 0.5","remove"]);
+        let session = session.lock().unwrap();
+        assert_eq!(session.board.to_fen(),Board::start_fen());
+        assert_eq!(session.limits().time_ms,Some(500));
+        assert!(!command(&Arc::new(Mutex::new(Session::new())),&mut searcher,"quit"));
+    }
+
+    #[test]
+    fn engine_move(){
+        let session = Arc::new(Mutex::new(Session::new()));
+        let mut searcher = Searcher::new();
+        send(&session,&mut searcher,&["new","sd 1","go"]);
+        // stopping lets the search play the best move it has
+        searcher.stop();
+        let session = session.lock().unwrap();
+        assert_eq!(session.history.len(),1);
+        assert!(!session.board.white_to_move());
+    }
+}
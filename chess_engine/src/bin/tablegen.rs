@@ -0,0 +1,38 @@
+// Generates endgame tables and writes them as <name>.srtb files
+// usage: tablegen [-o directory] KQvK KRvK KRvKP ...
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use chess_engine::EndgameTables;
+
+fn main(){
+    let mut out = PathBuf::from(".");
+    let mut names = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next(){
+        if arg == "-o"{
+            out = PathBuf::from(args.next().expect("missing directory after -o"));
+        }else{
+            names.push(arg);
+        }
+    }
+    if names.is_empty(){
+        eprintln!("usage: tablegen [-o directory] KQvK KRvK ...");
+        std::process::exit(2);
+    }
+
+    let mut tables = EndgameTables::new();
+    for name in names.iter(){
+        if !tables.generate(name){
+            eprintln!("cannot generate {}, expected material like KRvKP with at most 4 pieces",name);
+            std::process::exit(1);
+        }
+    }
+    for name in tables.names(){
+        let path = out.join(format!("{}.srtb",name));
+        fs::write(&path,tables.to_bytes(&name).unwrap()).unwrap_or_else(|e| panic!("cannot write {}: {}",path.display(),e));
+        println!("{}",path.display());
+    }
+}
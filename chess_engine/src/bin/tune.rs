@@ -0,0 +1,95 @@
+// Tunes the evaluation parameters on labelled positions and writes them to a parameter file
+// usage: tune <positions> <output.json|output.toml> [--params start] [--iterations n] [--rate cp]
+//
+// Every line of the positions file is a FEN followed by the result for white, as "1-0", "1/2-1/2",
+// "0-1" or as 1.0, 0.5, 0.0, optionally quoted or in brackets. EPD lines like
+// `<fen> c9 "1-0";` work as well.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use chess_engine::{Board,EvalParams,Tuner};
+
+fn fail(message:String)->!{
+    eprintln!("{}",message);
+    std::process::exit(2);
+}
+
+fn parse_result(token:&str)->Option<f64>{
+    match token.trim_matches(|c| matches!(c,'"' | '[' | ']' | ';')){
+        "1-0" | "1" | "1.0"=>Some(1.),
+        "1/2-1/2" | "0.5"=>Some(0.5),
+        "0-1" | "0" | "0.0"=>Some(0.),
+        _=>None,
+    }
+}
+
+fn parse_line(line:&str)->Result<(Board,f64),String>{
+    let fields:Vec<&str> = line.split_whitespace().collect();
+    let result = fields.last().and_then(|token| parse_result(token)).ok_or("no result at the end of the line")?;
+    // placement, side, castling and en passant are all the evaluation needs
+    let board = Board::from_fen(&fields[..fields.len().min(4)].join(" "))?;
+    Ok((board,result))
+}
+
+fn option<T:std::str::FromStr>(args:&mut Vec<String>,name:&str)->Option<T>{
+    let i = args.iter().position(|arg| arg == name)?;
+    if i + 1 >= args.len(){
+        fail(format!("{} needs a value",name));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value.parse().unwrap_or_else(|_| fail(format!("invalid value for {}: {}",name,value))))
+}
+
+fn main(){
+    let mut args:Vec<String> = env::args().skip(1).collect();
+    let start:Option<String> = option(&mut args,"--params");
+    let iterations:usize = option(&mut args,"--iterations").unwrap_or(2000);
+    let rate:f64 = option(&mut args,"--rate").unwrap_or(1.);
+    if args.len() != 2{
+        fail("usage: tune <positions> <output.json|output.toml> [--params start] [--iterations n] [--rate cp]".to_string());
+    }
+
+    let params = match start{
+        Some(path)=>EvalParams::load(Path::new(&path)).unwrap_or_else(|e| fail(format!("invalid parameters: {}",e))),
+        None=>EvalParams::default(),
+    };
+    let text = fs::read_to_string(&args[0]).unwrap_or_else(|e| fail(format!("{}: {}",args[0],e)));
+
+    let mut tuner = Tuner::new(&params);
+    let mut skipped = 0;
+    for (number,line) in text.lines().enumerate(){
+        if line.trim().is_empty(){
+            continue;
+        }
+        match parse_line(line){
+            Ok((board,result))=>tuner.add_position(&board,result),
+            Err(e)=>{
+                if skipped < 10{
+                    eprintln!("line {}: {}",number + 1,e);
+                }
+                skipped += 1;
+            }
+        }
+    }
+    if tuner.is_empty(){
+        fail("no usable positions".to_string());
+    }
+    println!("{} positions, {} skipped",tuner.len(),skipped);
+
+    println!("scale {:.1}, error {:.6}",tuner.fit_scale(),tuner.error());
+    for i in 1..=iterations{
+        tuner.step(rate);
+        if i % 100 == 0 || i == iterations{
+            println!("iteration {}, error {:.6}",i,tuner.error());
+        }
+    }
+
+    let tuned = tuner.params();
+    let output = Path::new(&args[1]);
+    let text = if output.extension().is_some_and(|ext| ext == "toml") {tuned.to_toml()} else {tuned.to_json()};
+    fs::write(output,text).unwrap_or_else(|e| fail(format!("{}: {}",output.display(),e)));
+    println!("written to {}",output.display());
+}
@@ -4,141 +4,173 @@ use std::vec;
 
 use crate::Board;
 use crate::PossibleMove;
+use crate::syzygy::{Tablebase,is_capture};
+use crate::rng::Rng;
+use crate::mate::find_mate_within;
+use crate::eval::Evaluator;
+use crate::nnue::Accumulator;
+use crate::score::Score;
+use crate::ordering;
 
 // use wasm_bindgen::prelude::*;
 use crate::*;
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 
-const BETA: f32 = 0.03;
+// for the first plies the bot picks among all moves this close to the best one
+const OPENING_PLIES: i32 = 8;
+const OPENING_MARGIN: f32 = 0.02;
 
+// keeps the mate pre-check fast, it gives up after this many positions
+const MATE_NODE_LIMIT: u64 = 20000;
 
-fn eval(board:Board,player:Color) -> f32{
+// how often the search checks the clock and reports progress
+const PROGRESS_STEPS: i32 = 64;
 
-    // let player_idx = player.to_num();
+// plies of captures the stronger levels play out before evaluating
+const QUIESCENCE_DEPTH: u32 = 4;
 
-    let mut vals :[f32;2] = [0.,0.];
+// the root counts as the first expansion, a search needs one more to have a move to play
+pub const MIN_EXPANSIONS: i32 = 2;
 
-    
-    for i in 0..64{
-        let tile = board.data[i];
-        let pos = Pos::from_num(i);
-        match tile{
-            Tile::Taken(color,piece,_)=>{
-                let val:f32 = match piece{
-                    Piece::Rook=>{
-                        let move_options = board.get_possible_moves_for_pos(pos, color, 0).len();
-                        5. + move_options as f32 *0.2
-                    }
-                    Piece::Pawn=>{
-
-                        let progress = if player == Color::White{
-                            pos.y 
-                        } else{
-                            7 - pos.y
-                        };
-
-                        1. + progress as f32 * 0.1
-                    }
-                    Piece::Knight=>{
-
-                        let center_values = [
-                            0,0,0,0, 0,0,0,0, 
-                            0,1,1,1, 1,1,1,0, 
-                            0,1,2,2, 2,2,1,0, 
-                            0,1,2,3, 3,2,1,0, 
-
-                            0,1,2,3, 3,2,1,0, 
-                            0,1,2,2, 2,2,1,0, 
-                            0,1,1,1, 1,1,1,0, 
-                            0,0,0,0, 0,0,0,0, 
-                        ];
-
-                        let positional_value = center_values[pos.num] as f32 * 0.3;
-
-                        3. + positional_value
-                    }
-                    Piece::Bishop=>{
-
-                        let move_options = board.get_possible_moves_for_pos(pos, color, 0).len();
-                        3. + move_options as f32 * 0.4
-                    }
-                    Piece::Queen=>{
-                        let move_options = board.get_possible_moves_for_pos(pos, color, 0).len();
-                        9. + move_options as f32 * 0.1
-                    }
-                    Piece::King=>{
-                        let early_king_values = [
-                            1,1,0,0, 0,0,1,1,
-                            0,0,0,0, 0,0,0,0,
-                            0,0,0,0, 0,0,0,0,
-                            0,0,0,0, 0,0,0,0,
-
-                            0,0,0,0, 0,0,0,0,
-                            0,0,0,0, 0,0,0,0,
-                            0,0,0,0, 0,0,0,0,
-                            1,1,0,0, 0,0,1,1,
-
-                        ];
-                        if board.counter < 10{
-                            early_king_values[pos.num] as f32 * 0.5
-                        }else{
-                            0.
-                        }
-                    }
-                };
-                vals[color.to_num()]+= val;
-            }
-            Tile::Empty=>{}
-        }
-    }
+// named playing strengths
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty{
+    Beginner,
+    Casual,
+    Intermediate,
+    Advanced,
+    Expert,
+}
 
+#[derive(Clone, Copy, Debug)]
+pub struct Strength{
+    // search expansions per move
+    pub expansions:i32,
+    // uniform noise added to every static evaluation
+    pub noise:f32,
+    // softmax temperature for picking among the root moves, 0 always plays the best one
+    pub temperature:f32,
+    pub use_tablebase:bool,
+    // mates up to this many moves are found by the exact solver before searching
+    pub mate_depth:u32,
+    // stops the search early, 0 for no limit
+    pub time_ms:u32,
+    // plies of captures played out before a new node is evaluated, 0 for the plain static evaluation
+    pub quiescence_depth:u32,
+}
 
-    // if vals[0] as i32 != board.value_counts[0]{
-    //     console_log!("deviant eval {:?} {:?} ",vals,board.value_counts);
-    // }
+// what a single search (a hint, an analysis) may spend
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits{
+    pub expansions:i32,
+    pub mate_depth:u32,
+    pub time_ms:u32,
+}
 
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SearchLimits{
 
+    /// Limits for one search: at least [`MIN_EXPANSIONS`] positions expanded (smaller counts are
+    /// raised to it), mates up to mate_depth moves solved exactly first (0 skips that) and a time limit
+    /// in milliseconds (0 for none).
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(expansions:i32,mate_depth:u32,time_ms:u32)->SearchLimits{
+        SearchLimits{expansions:expansions.max(MIN_EXPANSIONS),mate_depth,time_ms}
+    }
+}
 
-    // advantage for white from -1 to +1
-    let white_advantage:f32 = (vals[0]-vals[1]) / (vals[0]+vals[1]);
+impl SearchLimits{
 
-    //if we are looking for analysis of black player reverse the result
-    let mut result = white_advantage;
-    // if player == Color::Black{
-    //     result = - result;
-    // }    
-    result = result * player.get_dir()  as f32;
+    // full strength within the limits
+    pub fn strength(&self)->Strength{
+        Strength{expansions:self.expansions.max(MIN_EXPANSIONS),noise:0.,temperature:0.,use_tablebase:true,mate_depth:self.mate_depth,time_ms:self.time_ms,quiescence_depth:QUIESCENCE_DEPTH}
+    }
+}
 
-    // console_log!("eval {} ",result);
+impl Difficulty{
 
-    
-    let mx = 0.99;
-    if result >= mx{
-        console_log!("too big eval");
-        mx
+    pub fn strength(&self)->Strength{
+        match self{
+            Difficulty::Beginner=>Strength{expansions:50,noise:0.3,temperature:0.15,use_tablebase:false,mate_depth:0,time_ms:0,quiescence_depth:0},
+            Difficulty::Casual=>Strength{expansions:200,noise:0.12,temperature:0.06,use_tablebase:false,mate_depth:1,time_ms:0,quiescence_depth:0},
+            Difficulty::Intermediate=>Strength{expansions:1000,noise:0.,temperature:0.,use_tablebase:true,mate_depth:2,time_ms:0,quiescence_depth:QUIESCENCE_DEPTH},
+            Difficulty::Advanced=>Strength{expansions:5000,noise:0.,temperature:0.,use_tablebase:true,mate_depth:2,time_ms:0,quiescence_depth:QUIESCENCE_DEPTH},
+            Difficulty::Expert=>Strength{expansions:20000,noise:0.,temperature:0.,use_tablebase:true,mate_depth:3,time_ms:0,quiescence_depth:QUIESCENCE_DEPTH},
+        }
+    }
 
-    }else if result <= -mx{
-        console_log!("too smol eval");
-        -mx
-    }else{
-        result
+    // a label for the level on a rating-like scale: an estimate meant as a target, not measured against
+    // rated opponents, so it must not be shown as an Elo rating
+    pub fn nominal_rating(&self)->u32{
+        match self{
+            Difficulty::Beginner=>600,
+            Difficulty::Casual=>900,
+            Difficulty::Intermediate=>1200,
+            Difficulty::Advanced=>1450,
+            Difficulty::Expert=>1650,
+        }
     }
+}
 
+// what the search needs besides the tree
+struct Context<'a>{
+    tablebase:Option<&'a Tablebase>,
+    evaluator:&'a Evaluator,
+    noise:f32,
+    quiescence_depth:u32,
+    rng:&'a mut Rng,
+}
 
-    // return result
-    // result
 
+// the tapered evaluation on the search scale for the given player, short of a decided result
+fn eval(board:Board,player:Color,evaluator:&Evaluator) -> f32{
+    let score = if player == Color::White {evaluator.evaluate(&board)} else {-evaluator.evaluate(&board)};
+    let mx = 0.99;
+    score.to_value().clamp(-mx,mx)
+}
 
+// captures for player from a position whose static evaluation is stand_pat, so a node is not scored in the
+// middle of an exchange; captures that lose material by SEE are not tried
+fn quiescence(board:&Board,player:Color,stand_pat:f32,mut alpha:f32,beta:f32,depth:u32,evaluator:&Evaluator)->f32{
+    if depth == 0 || stand_pat >= beta{
+        return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+    let mut board = *board;
+    let mut captures:Vec<PossibleMove> = board.get_legal_moves(5).into_iter()
+        .filter(|mov| is_capture(&board,mov) && board.see(mov) >= 0)
+        .collect();
+    ordering::sort_static(&board,&mut captures);
+    for mov in captures{
+        let mut next = board;
+        next.make_possible_move(&mov);
+        let value = match next.state{
+            GameState::Won(winner)=>if winner == player {1.} else {-1.},
+            GameState::Draw=>0.,
+            GameState::Ongoing=>-quiescence(&next,player.other(),eval(next,player.other(),evaluator),-beta,-alpha,depth - 1,evaluator),
+        };
+        if value >= beta{
+            return value;
+        }
+        alpha = alpha.max(value);
+    }
+    alpha
 }
 
+#[cfg(feature = "wasm")]
 #[derive(Debug)]
 struct Chain{
     prev: Option<Box<Chain>>,
 }
 
 
+// runs when the webapp loads the module
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
 pub fn pre_bot(){
     // let board = Board::from_nums([
@@ -165,47 +197,188 @@ pub fn pre_bot(){
 
     first = Chain{ prev : Option::Some(Box::new(second))};
 
-    console_log!("chain start {:?} ",first.prev);
+    debug_log!("chain start {:?} ",first.prev);
 
     
     
 
-    // console_log!("{}\n white eval : {}",board,eval(board,Color::White));
+    // debug_log!("{}\n white eval : {}",board,eval(board,Color::White));
 }
 
 
 
 
 
-pub fn choose_move(board:Board)->PossibleMove{
+// why the move was chosen
+#[derive(Clone,Copy,Debug)]
+pub enum Reason{
+    Search,
+    // mate in this many moves
+    Mate(usize),
+    // tablebase distance, positive when winning
+    Tablebase(i32),
+}
 
+pub struct Choice{
+    pub mov:PossibleMove,
+    // for the side that moves
+    pub score:Score,
+    pub reason:Reason,
+    // the answer we expect from the opponent
+    pub reply:Option<PossibleMove>,
+    pub ponder:Option<Ponder>,
+}
 
-    // let depth = 100000;
-    let depth = 1e3 as i32;
+// searches for the side to move
+pub fn choose_move(board:Board,tablebase:&Tablebase,evaluator:&Evaluator,strength:&Strength,rng:&mut Rng,reuse:Option<Search>)->Choice{
+    choose_move_with(board,tablebase,evaluator,strength,rng,reuse,&mut |_| true)
+}
+
+// choose_move showing the tree to progress every PROGRESS_STEPS expansions, the search stops early when it
+// returns false
+pub fn choose_move_with(board:Board,tablebase:&Tablebase,evaluator:&Evaluator,strength:&Strength,rng:&mut Rng,reuse:Option<Search>,progress:&mut dyn FnMut(&Search)->bool)->Choice{
 
+    let tablebase = if strength.use_tablebase {Some(tablebase)} else {None};
 
-    let mut root = SearchNode::new(board,Color::Black);
+    if let Some((mov,dtz)) = tablebase.and_then(|tb| tb.best_move(&board)){
+        debug_log!("tablebase move, distance {}",dtz);
+        return Choice{mov,score:Score::tablebase(dtz),reason:Reason::Tablebase(dtz),reply:None,ponder:None};
+    }
+
+    if let Some(line) = find_mate_within(&board,strength.mate_depth,MATE_NODE_LIMIT){
+        debug_log!("mate in {}",line.len().div_ceil(2));
+        return Choice{mov:line[0].clone(),score:Score::mate_in(line.len() as u32),reason:Reason::Mate(line.len().div_ceil(2)),reply:line.get(1).cloned(),ponder:None};
+    }
+
+    let mut ctx = Context{tablebase,evaluator,noise:strength.noise,quiescence_depth:strength.quiescence_depth,rng};
+    let player = Color::from_num(board.counter as usize % 2);
+
+    // a pondered tree for this position already holds some of the work
+    let mut search = match reuse{
+        Some(search) if search.root.data.counter == board.counter=>{
+            debug_log!("reusing {} pondered expansions",search.root.n);
+            Search{root:search.root.searched()}
+        }
+        _=>Search{root:SearchNode::new(board,player,None,&mut ctx).searched()},
+    };
+
+    // let depth = 100000;
+    let depth = strength.expansions.max(MIN_EXPANSIONS);
+    let deadline = (strength.time_ms > 0).then(|| now_ms() + strength.time_ms as f64);
 
-    for i in 0..depth{
+    // a reused root may already count more expansions than allowed without a single child
+    while (search.root.n as i32) < depth || (search.root.children.is_empty() && search.root.data.state == GameState::Ongoing){
         // if i % (depth/5) == 0{
-        //     console_log!("expand {} ",i);
+        //     debug_log!("expand {} ",i);
         // } 
-        root.expand();
+        search.root.expand(&mut ctx);
+        if search.root.n as i32 % PROGRESS_STEPS == 0 && (deadline.is_some_and(|deadline| now_ms() > deadline) || !progress(&search)){
+            break;
+        }
     };
+    let mut root = search.root;
 
+    let idx = if strength.temperature > 0.{
+        let idx = root.sample_child(strength.temperature,ctx.rng);
+        debug_log!("playing sampled move {} of {}",idx,root.children.len());
+        idx
+    }else{
+        let margin = if board.counter < OPENING_PLIES {OPENING_MARGIN} else {0.};
+        root.pick_best(margin,ctx.rng)
+    };
 
+    debug_log!("confidence: {} ",root.r/root.n );
 
-    let (mov, mut future) = root.get_future();
+    let mov = root.possible_moves[idx].clone();
+    let mut future = root.children.swap_remove(idx);
+    let score = Score::from_value(- future.r / future.n);
 
-    console_log!("confidence: {} ",root.r/root.n );
+    // the reply we expect is where pondering continues
+    if future.children.is_empty(){
+        return Choice{mov,score,reason:Reason::Search,reply:None,ponder:None};
+    }
+    let reply = future.pick_best(0.,ctx.rng);
+    let expected = future.possible_moves[reply].clone();
+    let future = future.children.swap_remove(reply);
 
-    (_,future) = future.get_future();
+    debug_log!("envisioned response: {}\n n {}\n eval {}",future.data,future.n,eval(future.data,player,evaluator));
 
-    console_log!("envisioned response: {}\n n {}\n eval {}",future.data,future.n,eval(future.data,Color::Black));
+    Choice{mov,score,reason:Reason::Search,reply:Some(expected.clone()),ponder:Some(Ponder{expected,search:Search{root:*future}})}
+}
 
-    return mov.clone();
+#[cfg(feature = "wasm")]
+fn now_ms()->f64{
+    js_sys::Date::now()
+}
 
+#[cfg(not(feature = "wasm"))]
+fn now_ms()->f64{
+    use std::time::{SystemTime,UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64() * 1000.).unwrap_or(0.)
+}
 
+// a search tree that keeps growing between calls
+pub struct Search{
+    root:SearchNode,
+}
+
+impl Search{
+
+    // a tree holding only the position, for the side to move
+    pub fn new(board:Board,tablebase:&Tablebase,evaluator:&Evaluator,strength:&Strength,rng:&mut Rng)->Search{
+        let tablebase = if strength.use_tablebase {Some(tablebase)} else {None};
+        let mut ctx = Context{tablebase,evaluator,noise:strength.noise,quiescence_depth:strength.quiescence_depth,rng};
+        let player = Color::from_num(board.counter as usize % 2);
+        Search{root:SearchNode::new(board,player,None,&mut ctx).searched()}
+    }
+
+    pub fn expand(&mut self,steps:i32,tablebase:&Tablebase,evaluator:&Evaluator,strength:&Strength,rng:&mut Rng){
+        let tablebase = if strength.use_tablebase {Some(tablebase)} else {None};
+        let mut ctx = Context{tablebase,evaluator,noise:strength.noise,quiescence_depth:strength.quiescence_depth,rng};
+        for _ in 0..steps{
+            self.root.expand(&mut ctx);
+        }
+    }
+
+    pub fn expansions(&self)->i32{
+        self.root.n as i32
+    }
+
+    // for the side to move, from the move searched most so far
+    pub fn score(&self)->Score{
+        match self.root.most_searched(){
+            Some(idx)=>{
+                let child = &self.root.children[idx];
+                Score::from_value(- child.r / child.n)
+            }
+            None=>Score::from_value(self.root.r / self.root.n),
+        }
+    }
+
+    // the move searched most for each side in turn, as far as the tree reaches
+    pub fn principal_variation(&self)->Vec<PossibleMove>{
+        let mut line = vec![];
+        let mut node = &self.root;
+        while let Some(idx) = node.most_searched(){
+            line.push(node.possible_moves[idx].clone());
+            node = &node.children[idx];
+        }
+        line
+    }
+}
+
+// search on the position after the reply we expect from the opponent
+pub struct Ponder{
+    pub expected:PossibleMove,
+    pub search:Search,
+}
+
+impl Ponder{
+
+    pub fn is_hit(&self,start:usize,end:usize,upgrade:u8)->bool{
+        self.expected.start.num == start && self.expected.end.num == end
+            && (self.expected.upgrade == 0 || self.expected.upgrade == upgrade)
+    }
 }
 
 
@@ -217,15 +390,43 @@ struct SearchNode{
     player:Color,
     data : Board,
     n : f32,
+    // sum of expected results (see Score::to_value) for player
     r : f32,
+    // exact result known from the tablebase
+    exact : Option<f32>,
+    // network sums for data, kept until every child has been created from them
+    accumulator : Option<Accumulator>,
 }
 
 
 impl SearchNode{
 
-    fn new(board:Board,color:Color)->SearchNode{
-
-        let eval = eval(board,color);
+    // parent is the position before and its accumulator, so the network only updates what the move changed
+    fn new(board:Board,color:Color,parent:Option<(&Board,&Accumulator)>,ctx:&mut Context)->SearchNode{
+
+        let accumulator = ctx.evaluator.network().map(|network| match parent{
+            Some((before,accumulator))=>network.update(accumulator,before,&board),
+            None=>network.accumulator(&board),
+        });
+
+        let exact = ctx.tablebase.and_then(|tb| tb.probe_wdl(&board)).map(|wdl| wdl.signum() as f32);
+        let eval = match exact{
+            Some(value)=>value,
+            None=>{
+                let mut static_eval = match (ctx.evaluator.network(),&accumulator){
+                    (Some(network),Some(accumulator))=>network.output(accumulator,color).to_value().clamp(-0.99,0.99),
+                    _=>eval(board,color,ctx.evaluator),
+                };
+                if ctx.quiescence_depth > 0 && board.state == GameState::Ongoing{
+                    static_eval = quiescence(&board,color,static_eval,-1.,1.,ctx.quiescence_depth,ctx.evaluator);
+                }
+                if ctx.noise > 0.{
+                    (static_eval + ctx.noise * (2. * ctx.rng.next_f32() - 1.)).clamp(-0.99,0.99)
+                }else{
+                    static_eval
+                }
+            }
+        };
         SearchNode { 
             possible_moves : vec![],
             children: vec![],
@@ -234,53 +435,79 @@ impl SearchNode{
             // eval:eval,
             r : eval,
             n : 1.,
+            exact,
+            accumulator,
         }
     }
 
-    fn get_future(&self)->(&PossibleMove,&Box<SearchNode>){
-        let mut best_eval = 2.;
-        let mut best_i = 0;
-        for i in 0..self.children.len(){
-            let child_eval = self.children[i].r / self.children[i].n;
-            if child_eval < best_eval{
-                best_eval = child_eval;
-                best_i = i;
-            }
+    // a root is expanded even when the tablebase knows its outcome: a win or draw alone does not say
+    // which move keeps it, the children's outcomes do
+    fn searched(mut self)->SearchNode{
+        self.exact = None;
+        self
+    }
+
+    // the child searched most, which the values settle on, None before any was created
+    fn most_searched(&self)->Option<usize>{
+        (0..self.children.len()).max_by(|&a,&b| self.children[a].n.total_cmp(&self.children[b].n))
+    }
+
+    // picks uniformly among the children within margin of the best one, so ties are not always the first move
+    fn pick_best(&self,margin:f32,rng:&mut Rng)->usize{
+        let values:Vec<f32> = self.children.iter().map(|child| child.r / child.n).collect();
+        let best = values.iter().cloned().fold(f32::MAX,f32::min);
+        let candidates:Vec<usize> = (0..values.len()).filter(|&i| values[i] <= best + margin).collect();
+        if candidates.is_empty(){
+            return 0;
         }
+        candidates[rng.below(candidates.len())]
+    }
 
-        return (&self.possible_moves[best_i], &self.children[best_i])
+    // picks a child with probability growing exponentially with its value for us
+    fn sample_child(&self,temperature:f32,rng:&mut Rng)->usize{
+        let values:Vec<f32> = self.children.iter().map(|child| - child.r / child.n).collect();
+        let best = values.iter().cloned().fold(f32::MIN,f32::max);
+        let weights:Vec<f32> = values.iter().map(|v| ((v - best) / temperature).exp()).collect();
+        let mut pick = rng.next_f32() * weights.iter().sum::<f32>();
+        for (i,weight) in weights.iter().enumerate(){
+            if pick < *weight{
+                return i;
+            }
+            pick -= weight;
+        }
+        weights.len().saturating_sub(1)
     }
 
-    fn expand(&mut self)->f32{
+    fn expand(&mut self,ctx:&mut Context)->f32{
 
 
         let r_delta:f32;
 
-        //is the game over?
-        if self.data.state != GameState::Ongoing{
+        if let Some(value) = self.exact{
             self.n += 1.;
+            self.r += value;
+            return value;
+        }
 
+        // a pondered root that was decided by the tablebase has not listed its moves yet
+        if self.possible_moves.is_empty() && self.data.state == GameState::Ongoing{
+            // the first children are the likely best ones, see ordering, and without any the game is over
+            self.possible_moves = self.data.get_legal_moves(5);
+            ordering::sort_static(&self.data,&mut self.possible_moves);
+        }
 
+        //is the game over?
+        if self.data.state != GameState::Ongoing{
+            self.n += 1.;
             r_delta = match self.data.state{
-                GameState::Won(winner)=>{
-                    if winner == self.player{
-                        1.
-                    }else{
-                        -1.
-                    }
-                }
-                _=>{console_log!("cant see end");
-                    panic!()}
-
+                GameState::Won(winner) if winner == self.player=>1.,
+                GameState::Won(_)=>-1.,
+                _=>0.,
             };
             self.r += r_delta;
             return r_delta;
         }
 
-
-        if self.n == 1. {
-            self.possible_moves = self.data.get_legal_moves(5);}
-
         self.n += 1.;
 
 
@@ -291,7 +518,11 @@ impl SearchNode{
 
             let mut new_board = self.data.clone();
             new_board.make_possible_move(&self.possible_moves[self.children.len()]);
-            let new_child = SearchNode::new(new_board,self.player.other());
+            let parent = self.accumulator.as_ref().map(|accumulator| (&self.data,accumulator));
+            let new_child = SearchNode::new(new_board,self.player.other(),parent,ctx);
+            if self.children.len() + 1 == self.possible_moves.len(){
+                self.accumulator = None;
+            }
             r_delta = - new_child.r;
             self.children.push(Box::new(new_child));
 
@@ -307,9 +538,9 @@ impl SearchNode{
                 let child = & self.children[i];
                 let mu = child.r/child.n ;
                 if mu < -1. || mu > 1.{
-                    console_log!("mu error {}",mu)
+                    debug_log!("mu error {}",mu)
                 }
-                let ucb = (1.-mu) + BETA * f32::sqrt(2.*f32::ln(self.n  )/child.n );
+                let ucb = (1.-mu) + ctx.evaluator.params().exploration as f32 * f32::sqrt(2.*f32::ln(self.n  )/child.n );
                 if ucb > best_ucb{
                     best_i = i as i32;
                     best_ucb = ucb;
@@ -319,7 +550,7 @@ impl SearchNode{
             //expand best child
             match best_i{
                 -1=>{
-                    // console_log!("{}",self.children.len());
+                    // debug_log!("{}",self.children.len());
                     return -1.;
                 }
                 i=> {
@@ -327,7 +558,7 @@ impl SearchNode{
                     let idx = i as usize;
                     let best_child = &mut self.children[idx];
 
-                    r_delta = - best_child.expand();
+                    r_delta = - best_child.expand(ctx);
                 }
             }
         }
@@ -352,3 +583,72 @@ impl Display for SearchNode{
         write!(f,"SN {:?} ",self.data)
     }
 }
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::tablegen::Outcome;
+
+    // the tablebase only knows the outcome, as with .rtbw files without their .rtbz: the root is still
+    // searched and the move chosen keeps the win
+    #[test]
+    fn wdl_root_is_searched(){
+        let mut tablebase = Tablebase::new();
+        assert!(tablebase.generated.generate("KQvK"));
+        let board = Board::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").unwrap();
+        let evaluator = Evaluator::default();
+        let strength = SearchLimits::new(300,0,0).strength();
+        let mut rng = Rng::new(1);
+        let mut search = Search::new(board,&tablebase,&evaluator,&strength,&mut rng);
+        search.expand(300,&tablebase,&evaluator,&strength,&mut rng);
+
+        let line = search.principal_variation();
+        assert!(!line.is_empty());
+        let next = board.play_move(&line[0]);
+        assert!(matches!(tablebase.generated.probe(&next),Some(Outcome::Loss(_))));
+        assert!(search.score() > Score::ZERO);
+    }
+
+    // a wdl table alone, the rook next to the black king must not be left hanging
+    #[test]
+    fn rtbw_only_root(){
+        let mut tablebase = Tablebase::new();
+        assert!(tablebase.add_file("KRvK.rtbw",crate::syzygy::tests::single_value_wdl(4,0)));
+        let board = Board::from_fen("8/8/8/8/8/8/1k6/R3K3 w - - 0 1").unwrap();
+        let evaluator = Evaluator::default();
+        let strength = SearchLimits::new(300,0,0).strength();
+        let mut rng = Rng::new(1);
+        let mut search = Search::new(board,&tablebase,&evaluator,&strength,&mut rng);
+        search.expand(300,&tablebase,&evaluator,&strength,&mut rng);
+
+        let line = search.principal_variation();
+        assert!(!line.is_empty());
+        assert_eq!(tablebase.probe_wdl(&board.play_move(&line[0])),Some(-2));
+        assert!(search.score() > Score::ZERO);
+    }
+
+    // too few expansions still give the root a child to play
+    #[test]
+    fn tiny_searches(){
+        let tablebase = Tablebase::new();
+        let evaluator = Evaluator::default();
+        let board = Board::new();
+        let mut rng = Rng::new(1);
+        assert_eq!(SearchLimits::new(0,0,0).expansions,MIN_EXPANSIONS);
+        for expansions in [-1,0,1]{
+            let mut strength = SearchLimits::new(expansions,0,0).strength();
+            choose_move(board,&tablebase,&evaluator,&strength,&mut rng,None);
+            strength.expansions = expansions;
+            choose_move(board,&tablebase,&evaluator,&strength,&mut rng,None);
+            strength.temperature = 0.1;
+            choose_move(board,&tablebase,&evaluator,&strength,&mut rng,None);
+        }
+
+        // a pondered root that already counts more expansions than the limit
+        let strength = SearchLimits::new(1,0,0).strength();
+        let mut search = Search::new(board,&tablebase,&evaluator,&strength,&mut rng);
+        search.root.n = 50.;
+        let choice = choose_move(board,&tablebase,&evaluator,&strength,&mut rng,Some(search));
+        assert!(board.parse_move(&board.san(&choice.mov)).is_ok());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,324 @@
+// Tapered evaluation: every piece has a midgame and an endgame value from piece-square tables, and the
+// two totals are blended by how much material is left (the game phase). The numbers come from
+// EvalParams, PeSTO's unless configured otherwise.
+//
+// Tables are written from white's side with a8 first, like a diagram. Black pieces read them mirrored,
+// so both colours are scored the same way. On top of the tables come the pawn structure terms in
+// pawns.rs, king safety in king.rs and mobility and threats in mobility.rs.
+
+use std::cell::RefCell;
+
+use crate::*;
+use crate::params::{EvalParams,Pair};
+use crate::pawns::{Pawns,PawnTable};
+use crate::king;
+use crate::mobility;
+use crate::attacks::AttackMap;
+use crate::nnue::Network;
+use crate::score::Score;
+
+// index into the piece-square tables for a board square (x=0 is the h-file) seen by the given colour
+pub(crate) fn table_square(num:usize,color:Color)->usize{
+    let pos = Pos::from_num(num);
+    let file = 7 - pos.x as usize;
+    let rank = if color == Color::White {pos.y as usize} else {7 - pos.y as usize};
+    (7 - rank) * 8 + file
+}
+
+// 0 for bare kings up to the max phase (24 by default) for the starting material
+pub(crate) fn game_phase(board:&Board,params:&EvalParams)->i32{
+    let phase:i32 = board.data.iter().map(|tile| match tile{
+        Tile::Taken(_,piece,_)=>*params.phase_inc.get(*piece),
+        Tile::Empty=>0,
+    }).sum();
+    phase.min(params.max_phase())
+}
+
+// the parts the evaluation is made of, reported separately by Board::eval_trace
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Term{
+    Material,
+    PieceSquare,
+    PawnStructure,
+    PassedPawns,
+    KingSafety,
+    KingActivity,
+    Mobility,
+    Threats,
+}
+
+impl Term{
+    pub fn name(&self)->&'static str{
+        match self{
+            Term::Material=>"material",
+            Term::PieceSquare=>"piece-square",
+            Term::PawnStructure=>"pawn structure",
+            Term::PassedPawns=>"passed pawns",
+            Term::KingSafety=>"king safety",
+            Term::KingActivity=>"king activity",
+            Term::Mobility=>"mobility",
+            Term::Threats=>"threats",
+        }
+    }
+}
+
+// the midgame and endgame parameters a term is read from (see EvalParams::weight)
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Weight{
+    Value(Piece),
+    Square(Piece,usize),
+    // by rank from the pawn's side
+    Passed(usize),
+    Connected(usize),
+    Doubled,
+    Isolated,
+    Backward,
+    PassedOwnKing,
+    PassedEnemyKing,
+    ShieldClose,
+    ShieldFar,
+    KingOpenFile,
+    KingHalfOpenFile,
+    // an enemy piece per square of the king zone it attacks
+    KingAttack(Piece),
+    KingPawnDistance,
+    // per safe square a piece attacks
+    Mobility(Piece),
+    // a piece attacked by an enemy pawn, for the attacker
+    PawnThreat,
+    // an undefended piece under attack, for the attacker
+    Hanging,
+}
+
+// receives every term as it is found with how often its weight counts, so the plain evaluation, the trace
+// and the tuner share one code path
+pub(crate) trait Collector{
+    fn add(&mut self,term:Term,weight:Weight,color:Color,count:i32);
+}
+
+// midgame and endgame sums per colour
+pub(crate) struct Totals<'a>{
+    params:&'a EvalParams,
+    pub(crate) mg:[i32;2],
+    pub(crate) eg:[i32;2],
+}
+
+impl Totals<'_>{
+    pub(crate) fn new(params:&EvalParams)->Totals<'_>{
+        Totals{params,mg:[0;2],eg:[0;2]}
+    }
+}
+
+impl Collector for Totals<'_>{
+    fn add(&mut self,_:Term,weight:Weight,color:Color,count:i32){
+        let Pair{mg,eg} = self.params.weight(weight);
+        self.mg[color.to_num()] += count * mg;
+        self.eg[color.to_num()] += count * eg;
+    }
+}
+
+// how often every weight counts per colour, for the tests of the single terms
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct Counts{
+    found:Vec<(String,Color,i32)>,
+}
+
+#[cfg(test)]
+impl Counts{
+    pub(crate) fn get(&self,weight:Weight,color:Color)->i32{
+        let name = format!("{:?}",weight);
+        self.found.iter().filter(|(w,c,_)| *w == name && *c == color).map(|(_,_,count)| count).sum()
+    }
+}
+
+#[cfg(test)]
+impl Collector for Counts{
+    fn add(&mut self,_:Term,weight:Weight,color:Color,count:i32){
+        self.found.push((format!("{:?}",weight),color,count));
+    }
+}
+
+fn collect_pieces(board:&Board,out:&mut impl Collector){
+    for (num,tile) in board.data.iter().enumerate(){
+        if let Tile::Taken(color,piece,_) = tile{
+            out.add(Term::Material,Weight::Value(*piece),*color,1);
+            out.add(Term::PieceSquare,Weight::Square(*piece,table_square(num,*color)),*color,1);
+        }
+    }
+}
+
+pub(crate) fn collect(board:&Board,out:&mut impl Collector){
+    collect_pieces(board,out);
+    let pawns = Pawns::new(board);
+    pawns.collect_structure(out);
+    collect_dynamic(board,&pawns,[pawns.passed(Color::White),pawns.passed(Color::Black)],out);
+}
+
+// the terms that depend on more than the pawns, never cached
+fn collect_dynamic(board:&Board,pawns:&Pawns,passed:[u64;2],out:&mut impl Collector){
+    Pawns::collect_king_proximity(board,passed,out);
+    let map = AttackMap::new(board);
+    king::collect(board,pawns,&map,out);
+    mobility::collect(board,&map,out);
+}
+
+fn taper(mg:i32,eg:i32,phase:i32,max_phase:i32)->i32{
+    (mg * phase + eg * (max_phase - phase)) / max_phase
+}
+
+// positive when white is better, the pawn-only terms come from the table when it has them
+pub(crate) fn evaluate(board:&Board,params:&EvalParams,pawn_table:&mut PawnTable)->Score{
+    let mut totals = Totals::new(params);
+    collect_pieces(board,&mut totals);
+    let pawns = Pawns::new(board);
+    let (mg,eg,passed) = pawn_table.probe(&pawns,params);
+    for color in 0..2{
+        totals.mg[color] += mg[color];
+        totals.eg[color] += eg[color];
+    }
+    collect_dynamic(board,&pawns,passed,&mut totals);
+    Score::centipawns(taper(totals.mg[0] - totals.mg[1],totals.eg[0] - totals.eg[1],game_phase(board,params),params.max_phase()))
+}
+
+// how the engine judges positions: the handcrafted terms, or the network when one is loaded
+#[derive(Clone, Debug, Default)]
+pub struct Evaluator{
+    params:EvalParams,
+    network:Option<Network>,
+    pawn_table:RefCell<PawnTable>,
+}
+
+impl Evaluator{
+
+    // positive when white is better
+    pub fn evaluate(&self,board:&Board)->Score{
+        match &self.network{
+            Some(network)=>network.evaluate(board),
+            None=>evaluate(board,&self.params,&mut self.pawn_table.borrow_mut()),
+        }
+    }
+
+    pub fn params(&self)->&EvalParams{
+        &self.params
+    }
+
+    pub fn set_params(&mut self,params:EvalParams){
+        self.params = params;
+        self.pawn_table.borrow_mut().clear();
+    }
+
+    pub fn network(&self)->Option<&Network>{
+        self.network.as_ref()
+    }
+
+    pub fn set_network(&mut self,network:Option<Network>){
+        self.network = network;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceRow{
+    pub term:Term,
+    // midgame and endgame centipawns, indexed by colour (white first)
+    pub mg:[i32;2],
+    pub eg:[i32;2],
+}
+
+#[derive(Clone, Debug)]
+pub struct EvalTrace{
+    pub phase:i32,
+    pub max_phase:i32,
+    pub rows:Vec<TraceRow>,
+}
+
+struct Tracer<'a>{
+    params:&'a EvalParams,
+    trace:EvalTrace,
+}
+
+impl Collector for Tracer<'_>{
+    fn add(&mut self,term:Term,weight:Weight,color:Color,count:i32){
+        let Pair{mg,eg} = self.params.weight(weight);
+        let rows = &mut self.trace.rows;
+        let row = match rows.iter().position(|row| row.term == term){
+            Some(i)=>&mut rows[i],
+            None=>{
+                rows.push(TraceRow{term,mg:[0;2],eg:[0;2]});
+                rows.last_mut().unwrap()
+            }
+        };
+        row.mg[color.to_num()] += count * mg;
+        row.eg[color.to_num()] += count * eg;
+    }
+}
+
+impl TraceRow{
+
+    // tapered difference white minus black
+    pub fn score(&self,phase:i32,max_phase:i32)->Score{
+        Score::centipawns(taper(self.mg[0] - self.mg[1],self.eg[0] - self.eg[1],phase,max_phase))
+    }
+}
+
+impl EvalTrace{
+
+    // the evaluation itself, up to rounding of the single terms
+    pub fn total(&self)->Score{
+        let mg:i32 = self.rows.iter().map(|row| row.mg[0] - row.mg[1]).sum();
+        let eg:i32 = self.rows.iter().map(|row| row.eg[0] - row.eg[1]).sum();
+        Score::centipawns(taper(mg,eg,self.phase,self.max_phase))
+    }
+}
+
+impl fmt::Display for EvalTrace{
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result{
+        let pawns = |cp:i32| format!("{:.2}",cp as f32 / 100.);
+        writeln!(f,"{:<14}|{:>8}{:>8} |{:>8}{:>8} |{:>8}","term","white mg","eg","black mg","eg","total")?;
+        writeln!(f,"{}","-".repeat(60))?;
+        for row in self.rows.iter(){
+            writeln!(f,"{:<14}|{:>8}{:>8} |{:>8}{:>8} |{:>8}",row.term.name(),
+                pawns(row.mg[0]),pawns(row.eg[0]),pawns(row.mg[1]),pawns(row.eg[1]),row.score(self.phase,self.max_phase).to_string())?;
+        }
+        writeln!(f,"{}","-".repeat(60))?;
+        write!(f,"phase {}/{}, total {} for white",self.phase,self.max_phase,self.total())
+    }
+}
+
+impl Board{
+
+    // every evaluation term per side and phase
+    pub fn eval_trace(&self)->EvalTrace{
+        self.eval_trace_with(&EvalParams::default())
+    }
+
+    pub fn eval_trace_with(&self,params:&EvalParams)->EvalTrace{
+        let mut tracer = Tracer{params,trace:EvalTrace{phase:game_phase(self,params),max_phase:params.max_phase(),rows:vec![]}};
+        collect(self,&mut tracer);
+        tracer.trace
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn trace_total_is_the_evaluation(){
+        let evaluator = Evaluator::default();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+            "6k1/5ppp/8/3n4/4P3/6P1/5P1P/6K1 b - - 0 1",
+            "8/5k2/8/3P4/8/8/1K6/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ]{
+            let board = Board::from_fen(fen).unwrap();
+            let trace = board.eval_trace();
+            assert_eq!(trace.total(),evaluator.evaluate(&board),"{}",fen);
+            // the second time the pawn terms come from the table
+            assert_eq!(trace.total(),evaluator.evaluate(&board),"{}",fen);
+        }
+    }
+}
@@ -1,47 +1,271 @@
 // use crate::bot::choose_move;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
-use crate::{console_log,log};
+use crate::debug_log;
 use crate::Board;
 use crate::GameState;
 use crate::bot;
+use crate::bot::{Difficulty,Reason,SearchLimits};
+use crate::syzygy::Tablebase;
+use crate::params::EvalParams;
+use crate::eval::{EvalTrace,Evaluator};
+use crate::nnue::Network;
+use crate::score::Score;
+use crate::rng::{self,Rng};
+use crate::review::{self,MoveReview};
+use crate::analysis::{self,GameAnalysis};
+use crate::PossibleMove;
 
 
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+pub struct Hint{
+    pub start:usize,
+    pub end:usize,
+    pub upgrade:u8,
+    pub san:String,
+    // for the player asking
+    pub score:Score,
+    pub explanation:String,
+}
+
+fn describe(score:Score)->&'static str{
+    match score.capped_centipawns(10000){
+        s if s > 300=>"is winning",
+        s if s > 120=>"gives a clear advantage",
+        s if s > 40=>"gives a slight advantage",
+        s if s >= -40=>"keeps the position balanced",
+        s if s >= -120=>"leaves you slightly worse",
+        s if s >= -300=>"leaves you clearly worse",
+        _=>"is the most stubborn defence in a lost position",
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Game{
     board: Board,
     winner: i8,
     succ_move:bool,
+    tablebase:Tablebase,
+    evaluator:Evaluator,
+    difficulty:Difficulty,
+    // set in deterministic mode, the move then only depends on seed and position
+    seed:Option<u64>,
+    rng:Rng,
+    pondering:bool,
+    ponder:Option<bot::Ponder>,
+    // budget for grading the human's moves, None (the default) turns it off
+    review_limits:Option<SearchLimits>,
+    last_review:Option<MoveReview>,
+    // every move played with the board it was played on
+    history:Vec<(Board,PossibleMove)>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Game{
 
     pub fn new()->Game{
-        Game{board:Board::new(),winner:-1,succ_move:true}
+        Game{board:Board::new(),winner:-1,succ_move:true,tablebase:Tablebase::new(),evaluator:Evaluator::default(),difficulty:Difficulty::Intermediate,seed:None,rng:Rng::from_entropy(),pondering:false,ponder:None,
+            review_limits:None,last_review:None,history:vec![]}
+    }
+
+    // grading searches on every human move and blocks until done, so the webapp should run the game in a worker
+    // before turning it on
+    pub fn set_review_limits(&mut self,limits:Option<SearchLimits>){
+        self.review_limits = limits;
+    }
+
+    // how good the last human move was, with the engine's alternative
+    pub fn get_last_review(&self)->Option<MoveReview>{
+        self.last_review.clone()
+    }
+
+    // when enabled the bot keeps the tree for the reply it expects, grown by calling ponder while the human thinks
+    pub fn set_pondering(&mut self,enabled:bool){
+        self.pondering = enabled;
+        if !enabled{
+            self.ponder = None;
+        }
+    }
+
+    // expands the ponder tree by some steps, returns its total size or 0 if there is nothing to ponder
+    pub fn ponder(&mut self,steps:i32)->i32{
+        match &mut self.ponder{
+            Some(ponder)=>{
+                ponder.search.expand(steps,&self.tablebase,&self.evaluator,&self.difficulty.strength(),&mut self.rng);
+                ponder.search.expansions()
+            }
+            None=>0,
+        }
+    }
+
+    // deterministic mode: the same seed and position always produce the same move
+    pub fn set_seed(&mut self,seed:u64){
+        self.seed = Some(seed);
+    }
+
+    pub fn clear_seed(&mut self){
+        self.seed = None;
     }
 
-    pub fn get_data(&self)->js_sys::Uint32Array{
+    pub fn set_difficulty(&mut self,difficulty:Difficulty){
+        self.difficulty = difficulty;
+    }
+
+    pub fn get_difficulty(&self)->Difficulty{
+        self.difficulty
+    }
+
+    // the nominal rating of the current difficulty, an uncalibrated estimate (see Difficulty::nominal_rating)
+    pub fn get_nominal_rating(&self)->u32{
+        self.difficulty.nominal_rating()
+    }
+
+    // evaluation weights as JSON (see EvalParams), fields left out keep their default
+    pub fn set_eval_params(&mut self,json:&str)->Result<(),String>{
+        self.evaluator.set_params(EvalParams::from_json(json)?);
+        // a pondered tree was grown with the old weights
+        self.ponder = None;
+        Ok(())
+    }
+
+    pub fn get_eval_params(&self)->String{
+        self.evaluator.params().to_json()
+    }
+
+    pub fn reset_eval_params(&mut self){
+        self.evaluator.set_params(EvalParams::default());
+        self.ponder = None;
+    }
+
+    // evaluates with this network (see nnue.rs for the file format) instead of the handcrafted terms
+    pub fn set_network(&mut self,data:Vec<u8>)->Result<(),String>{
+        self.evaluator.set_network(Some(Network::from_bytes(&data)?));
+        self.ponder = None;
+        Ok(())
+    }
+
+    // back to the handcrafted evaluation
+    pub fn clear_network(&mut self){
+        self.evaluator.set_network(None);
+        self.ponder = None;
+    }
+
+    pub fn has_network(&self)->bool{
+        self.evaluator.network().is_some()
+    }
+
+    // adds a syzygy table file (e.g. "KQvK.rtbw") fetched by the frontend
+    pub fn add_tablebase_file(&mut self,name:&str,data:Vec<u8>)->bool{
+        self.tablebase.add_file(name, data)
+    }
+
+    // generates an endgame table like "KRvK" (and the ones it converts into) in the browser. this blocks the
+    // thread it runs on, so wasm builds stop at three pieces: four piece tables come from the tablegen binary
+    // through add_endgame_table
+    pub fn generate_endgame_table(&mut self,name:&str)->bool{
+        let pieces = name.chars().filter(|c| *c != 'v').count();
+        if cfg!(target_arch = "wasm32") && pieces > 3{
+            return false;
+        }
+        self.tablebase.generated.generate(name)
+    }
+
+    pub fn add_endgame_table(&mut self,data:Vec<u8>)->bool{
+        self.tablebase.generated.add_bytes(&data).is_some()
+    }
+
+    pub fn export_endgame_table(&self,name:&str)->Option<Vec<u8>>{
+        self.tablebase.generated.to_bytes(name)
+    }
+
+    // a forced mate for the side to move in at most the given number of moves, in coordinate notation, empty if there is none
+    pub fn find_mate(&self,moves:u32)->Vec<String>{
+        match self.board.find_mate(moves){
+            Some(line)=>self.board.line_to_coordinates(&line),
+            None=>vec![],
+        }
+    }
+
+    // the engine's suggestion for the side to move, the game itself is left untouched
+    pub fn hint(&self,limits:&SearchLimits)->Option<Hint>{
+        if self.board.state != GameState::Ongoing{
+            return None;
+        }
+        let mut rng = Rng::new(rng::position_seed(self.seed.unwrap_or(0),&self.board));
+        let choice = bot::choose_move(self.board,&self.tablebase,&self.evaluator,&limits.strength(),&mut rng,None);
+
+        let san = self.board.san(&choice.mov);
+        let mut explanation = match choice.reason{
+            Reason::Mate(moves)=>format!("{} forces mate in {}.",san,moves),
+            Reason::Tablebase(distance) if distance > 0=>format!("{} wins according to the endgame tables.",san),
+            Reason::Tablebase(0)=>format!("{} holds the draw according to the endgame tables.",san),
+            Reason::Tablebase(_)=>format!("{} resists longest according to the endgame tables.",san),
+            Reason::Search=>format!("{} {} (score {}).",san,describe(choice.score),choice.score),
+        };
+        if let Some(reply) = &choice.reply{
+            let mut next = self.board;
+            next.make_possible_move(&choice.mov);
+            explanation += &format!(" Expected reply: {}.",next.san(reply));
+        }
+
+        Some(Hint{
+            start:choice.mov.start.num,
+            end:choice.mov.end.num,
+            upgrade:choice.mov.upgrade,
+            san,
+            score:choice.score,
+            explanation,
+        })
+    }
+
+    // grades every move of the game so far, see GameAnalysis for the report formats
+    pub fn analyze(&self,limits:&SearchLimits)->GameAnalysis{
+        analysis::analyze(&self.history,&self.board,&self.tablebase,&self.evaluator,limits,self.seed.unwrap_or(0))
+    }
+
+    // squares of white's or black's pieces the other side wins material by taking, for a "hanging piece" warning
+    pub fn hanging_pieces(&self,white:bool)->Vec<usize>{
+        self.board.hanging_pieces(white)
+    }
+
+    pub fn get_data(&self)->Vec<u32>{
         self.board.get_data()
     }
 
-    pub fn make_move(&mut self,start:usize,end:usize,upgrade:u8)->js_sys::Uint32Array{
+    pub fn make_move(&mut self,start:usize,end:usize,upgrade:u8)->Vec<u32>{
 
-        if matches!(self.board.state,GameState::Won(_)){
+        if self.board.state != GameState::Ongoing{
             return self.board.get_data();
         }
 
+        let before = self.board;
         let counter = self.board.counter;
         self.board = self.board.update(start, end, upgrade);
         if counter == self.board.counter{
             self.succ_move = false
         }else{
-            self.succ_move = true
+            self.succ_move = true;
+            let played = review::find_move(&before,start,end,upgrade);
+            self.last_review = match (self.review_limits,&played){
+                (Some(limits),Some(played))=>{
+                    let mut rng = Rng::new(rng::position_seed(self.seed.unwrap_or(0),&before));
+                    Some(review::review_move(&before,played,&self.tablebase,&self.evaluator,&limits,&mut rng))
+                }
+                _=>None,
+            };
+            self.history.extend(played.map(|played| (before,played)));
+            if let Some(ponder) = self.ponder.take(){
+                if ponder.is_hit(start,end,upgrade){
+                    debug_log!("ponder hit");
+                    self.ponder = Some(ponder);
+                }
+            }
         }
         return self.get_data();
 
     }
-    pub fn respond(&mut self)->js_sys::Uint32Array{
+    pub fn respond(&mut self)->Vec<u32>{
 
 
         if ! self.succ_move{
@@ -49,13 +273,13 @@ impl Game{
         }
 
         if self.board.get_legal_moves(5).len() == 0{
-            console_log!("GAME OVER");
+            debug_log!("GAME OVER");
             return self.get_data();
         }
 
         match self.board.state{
             GameState::Won(color)=>{
-                console_log!("{} won.",color);
+                debug_log!("{} won.",color);
                 self.winner = color.to_num() as i8;
                 return self.get_data()
             }
@@ -64,12 +288,91 @@ impl Game{
 
 
 
-        let bot_move = bot::choose_move(self.board);
+        let mut seeded = self.seed.map(|seed| Rng::new(rng::position_seed(seed,&self.board)));
+        let rng = seeded.as_mut().unwrap_or(&mut self.rng);
+        // pondered work depends on timing, so deterministic mode always searches from scratch
+        let reuse = self.ponder.take().filter(|_| self.seed.is_none()).map(|ponder| ponder.search);
+        let choice = bot::choose_move(self.board,&self.tablebase,&self.evaluator,&self.difficulty.strength(),rng,reuse);
+        if self.pondering{
+            self.ponder = choice.ponder;
+        }
 
-        // console_log!("making bot move");
+        // debug_log!("making bot move");
 
-        self.board.make_possible_move(&bot_move);
+        self.history.push((self.board,choice.mov.clone()));
+        self.board.make_possible_move(&choice.mov);
 
         self.get_data()
     }
 }
+
+impl Game{
+
+    // the evaluation of the position term by term, see eval_trace for the webapp
+    pub fn trace(&self)->EvalTrace{
+        self.board.eval_trace_with(self.evaluator.params())
+    }
+
+    // for white, None without a network
+    pub fn network_score(&self)->Option<Score>{
+        self.evaluator.network().map(|network| network.evaluate(&self.board))
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl Game{
+
+    // {phase, maxPhase, total, terms:[{name, white:{mg,eg}, black:{mg,eg}, total}], network} in centipawns, totals for white,
+    // network only when one is loaded
+    pub fn eval_trace(&self)->JsValue{
+        let trace = self.trace();
+        let set = |object:&js_sys::Object,key:&str,value:JsValue|{
+            js_sys::Reflect::set(object,&JsValue::from_str(key),&value).unwrap();
+        };
+        let side = |mg:i32,eg:i32|{
+            let object = js_sys::Object::new();
+            set(&object,"mg",mg.into());
+            set(&object,"eg",eg.into());
+            object
+        };
+        let terms = js_sys::Array::new();
+        for row in trace.rows.iter(){
+            let term = js_sys::Object::new();
+            set(&term,"name",row.term.name().into());
+            set(&term,"white",side(row.mg[0],row.eg[0]).into());
+            set(&term,"black",side(row.mg[1],row.eg[1]).into());
+            set(&term,"total",row.score(trace.phase,trace.max_phase).raw().into());
+            terms.push(&term);
+        }
+        let res = js_sys::Object::new();
+        set(&res,"phase",trace.phase.into());
+        set(&res,"maxPhase",trace.max_phase.into());
+        set(&res,"total",trace.total().raw().into());
+        set(&res,"terms",terms.into());
+        if let Some(score) = self.network_score(){
+            set(&res,"network",score.raw().into());
+        }
+        res.into()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Game{
+    pub fn add_tablebase_directory(&mut self,path:&str)->std::io::Result<usize>{
+        self.tablebase.add_directory(std::path::Path::new(path))
+    }
+
+    // a JSON or TOML file of evaluation weights
+    pub fn load_eval_params(&mut self,path:&str)->Result<(),String>{
+        self.evaluator.set_params(EvalParams::load(std::path::Path::new(path))?);
+        self.ponder = None;
+        Ok(())
+    }
+
+    pub fn load_network(&mut self,path:&str)->Result<(),String>{
+        self.evaluator.set_network(Some(Network::load(std::path::Path::new(path))?));
+        self.ponder = None;
+        Ok(())
+    }
+}
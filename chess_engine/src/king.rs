@@ -0,0 +1,130 @@
+// King safety and king activity.
+//
+// In the middlegame a king wants pawns in front of it, no open files next to it and few enemy pieces
+// bearing on the squares around it. In the endgame it should come out and walk to the pawns instead.
+// The safety weights are midgame values and the activity weight an endgame value, so the taper hands
+// over from one to the other as material comes off the board.
+
+use crate::*;
+use crate::attacks::{self,AttackMap};
+use crate::eval::{Collector,Term,Weight};
+use crate::pawns::{self,Pawns};
+
+// the king's square, the squares around it and the three in front of those
+fn zone(king:usize,color:Color)->u64{
+    let around = attacks::attacks(Piece::King,color,king,0) | 1 << king;
+    let forward = if color == Color::White {around << 8} else {around >> 8};
+    around | forward
+}
+
+pub(crate) fn collect(board:&Board,pawns:&Pawns,map:&AttackMap,out:&mut impl Collector){
+    for color in [Color::White,Color::Black]{
+        let Some(king) = board.data.iter().position(|tile| matches!(tile,Tile::Taken(c,Piece::King,_) if *c == color)) else{
+            continue;
+        };
+        let (own_pawns,enemy_pawns) = (pawns.bits(color),pawns.bits(color.other()));
+        let (x,y) = (king as i32 % 8,king as i32 / 8);
+        let forward = if color == Color::White {1} else {-1};
+
+        // the king's file and the files next to it
+        for file in (x - 1).max(0)..=(x + 1).min(7){
+            let file_bits = pawns::file_mask(file as usize);
+            for (distance,weight) in [(1,Weight::ShieldClose),(2,Weight::ShieldFar)]{
+                let rank = y + forward * distance;
+                if (0..8).contains(&rank) && own_pawns & 1 << (rank * 8 + file) != 0{
+                    out.add(Term::KingSafety,weight,color,1);
+                }
+            }
+            if own_pawns & file_bits == 0{
+                let weight = if enemy_pawns & file_bits == 0 {Weight::KingOpenFile} else {Weight::KingHalfOpenFile};
+                out.add(Term::KingSafety,weight,color,1);
+            }
+        }
+
+        // enemy pieces by the number of squares of the zone they hit
+        let zone = zone(king,color);
+        for (num,tile) in board.data.iter().enumerate(){
+            if let Tile::Taken(c,piece,_) = tile{
+                if *c != color{
+                    let hits = (map.piece_attacks(num) & zone).count_ones() as i32;
+                    if hits > 0{
+                        out.add(Term::KingSafety,Weight::KingAttack(*piece),color,hits);
+                    }
+                }
+            }
+        }
+
+        // in the endgame, how far the king is from the nearest pawn
+        let nearest = board.data.iter().enumerate()
+            .filter(|(_,tile)| matches!(tile,Tile::Taken(_,Piece::Pawn,_)))
+            .map(|(num,_)| pawns::distance(king,num))
+            .min();
+        if let Some(nearest) = nearest{
+            out.add(Term::KingActivity,Weight::KingPawnDistance,color,nearest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::eval::Counts;
+    use crate::notation::parse_square;
+
+    fn terms(fen:&str)->Counts{
+        let board = Board::from_fen(fen).unwrap();
+        let mut counts = Counts::default();
+        collect(&board,&Pawns::new(&board),&AttackMap::new(&board),&mut counts);
+        counts
+    }
+
+    #[test]
+    fn shield_and_files(){
+        let counts = terms("6k1/5ppp/8/8/8/6P1/5P1P/6K1 w - - 0 1");
+        assert_eq!(counts.get(Weight::ShieldClose,Color::White),2);
+        assert_eq!(counts.get(Weight::ShieldFar,Color::White),1);
+        assert_eq!(counts.get(Weight::ShieldClose,Color::Black),3);
+        assert_eq!(counts.get(Weight::KingOpenFile,Color::White),0);
+        assert_eq!(counts.get(Weight::KingHalfOpenFile,Color::White),0);
+
+        // only black has a pawn on the f-file, nobody on the e-file
+        let counts = terms("4k3/5ppp/8/8/8/8/6PP/6K1 w - - 0 1");
+        assert_eq!(counts.get(Weight::ShieldClose,Color::White),2);
+        assert_eq!(counts.get(Weight::KingHalfOpenFile,Color::White),1);
+        assert_eq!(counts.get(Weight::KingOpenFile,Color::White),0);
+        assert_eq!(counts.get(Weight::ShieldClose,Color::Black),1);
+        assert_eq!(counts.get(Weight::KingOpenFile,Color::Black),2);
+        // a king on the edge has two files
+        let counts = terms("4k3/8/8/8/8/8/8/7K w - - 0 1");
+        assert_eq!(counts.get(Weight::KingOpenFile,Color::White),2);
+        assert_eq!(counts.get(Weight::KingOpenFile,Color::Black),3);
+    }
+
+    #[test]
+    fn zone_attacks(){
+        let king = parse_square("g1").unwrap();
+        let squares = ["f1","g1","h1","f2","g2","h2","f3","g3","h3"];
+        assert_eq!(zone(king,Color::White),squares.iter().fold(0,|bits,name| bits | 1 << parse_square(name).unwrap()));
+        assert_eq!(zone(parse_square("g8").unwrap(),Color::Black).count_ones(),9);
+
+        // the knight hits f3 and h3, the rook g3 and the pawn on g2, the bishop passes the zone
+        let counts = terms("6k1/5ppp/8/6n1/6r1/8/1b3PPP/6K1 w - - 0 1");
+        assert_eq!(counts.get(Weight::KingAttack(Piece::Knight),Color::White),2);
+        assert_eq!(counts.get(Weight::KingAttack(Piece::Rook),Color::White),2);
+        assert_eq!(counts.get(Weight::KingAttack(Piece::Bishop),Color::White),0);
+        assert_eq!(counts.get(Weight::KingAttack(Piece::Pawn),Color::White),0);
+        // nothing of white's reaches the black king
+        assert_eq!(counts.get(Weight::KingAttack(Piece::Pawn),Color::Black),0);
+        assert_eq!(counts.get(Weight::KingAttack(Piece::King),Color::Black),0);
+    }
+
+    #[test]
+    fn pawn_distance(){
+        let counts = terms("k7/8/8/8/4P3/8/8/K7 w - - 0 1");
+        assert_eq!(counts.get(Weight::KingPawnDistance,Color::White),4);
+        assert_eq!(counts.get(Weight::KingPawnDistance,Color::Black),4);
+        // without pawns the term is left out
+        let counts = terms("k7/8/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(counts.get(Weight::KingPawnDistance,Color::White),0);
+    }
+}
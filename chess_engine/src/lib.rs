@@ -1,24 +1,49 @@
 mod bot;
 mod game;
+mod syzygy;
+mod tablegen;
+mod rng;
+mod notation;
+mod mate;
+mod review;
+mod analysis;
+mod eval;
+mod score;
+mod params;
+mod tune;
+mod nnue;
+mod pawns;
+mod king;
+mod attacks;
+mod mobility;
+mod see;
+mod ordering;
+mod pgn;
+mod logger;
+pub mod api;
 
 pub use game::*;
-
-// use crate::game::console_log
-
+pub use bot::{choose_move,choose_move_with,Choice,Difficulty,MIN_EXPANSIONS,Ponder,Reason,Search,SearchLimits,Strength};
+pub use rng::Rng;
+pub use review::{MoveQuality,MoveReview};
+pub use analysis::{AnalyzedMove,GameAnalysis};
+pub use score::Score;
+pub use eval::{EvalTrace,Evaluator,Term,TraceRow};
+pub use nnue::Network;
+pub use params::{EvalParams,PieceMap};
+pub use tune::Tuner;
+pub use attacks::AttackMap;
+pub use tablegen::{EndgameTables,Outcome};
+pub use syzygy::Tablebase;
+pub use pgn::{game_result,read_pgn,write_pgn};
+pub use logger::{log,reset_logger,set_logger};
+
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use std::{fmt::{self}};
 use strum_macros::Display;
 
-#[wasm_bindgen]
-extern "C"{
-    #[wasm_bindgen(js_namespace = console)]
-    pub fn log(s: &str);
-}
-
-macro_rules! console_log {
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()));
-}
-pub(crate) use console_log;
+pub(crate) use logger::debug_log;
 
 const DIM:usize = 64;
 const KNIGHT_HOPS:[(i8,i8);8] =  [(1,2),(2,1),(1,-2),(2,-1),(-1,-2),(-2,-1),(-1,2),(-2,1)];
@@ -114,7 +139,7 @@ impl Tile{
                         5=>Piece::Queen,
                         6=>Piece::Pawn,
                         _=>{
-                            console_log!("cannot find piece for {}",x);
+                            debug_log!("cannot find piece for {}",x);
                             panic!()}
                     }, 
                     PieceInfo::None
@@ -216,11 +241,12 @@ impl PossibleMove{
 #[derive(Display,Clone, Copy,Debug,PartialEq)]
 enum GameState {
     Won(Color),
-    // Draw,
+    // stalemate
+    Draw,
     Ongoing,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Clone, Copy,Debug)]
 pub struct Board{
     counter: i32,
@@ -397,18 +423,18 @@ impl Board{
 
                         Tile::Taken(color,piece,_)=>{
                             if color == enemy_color && (matches!(piece, Piece::Rook) || matches!(piece,Piece::Queen)){
-                                // console_log!("checking for straight rook or queen for {} ",start_pos);
-                                // console_log!("hit on {}",new_pos);
+                                // debug_log!("checking for straight rook or queen for {} ",start_pos);
+                                // debug_log!("hit on {}",new_pos);
                                 return false
                             }else{
-                                // console_log!("stop on {} {} ",new_pos,piece);
+                                // debug_log!("stop on {} {} ",new_pos,piece);
                                 break
                             }
                         }
                         Tile::Empty=> pos = new_pos
                     }
                     Err(_)=>{
-                        // console_log!("end on {}",pos);
+                        // debug_log!("end on {}",pos);
                         break
                     }
         }}}
@@ -533,7 +559,7 @@ impl Board{
                         self.try_pawn_takes(start_pos, 1, dir, color, &mut result,upgrade);
                         self.try_pawn_takes(start_pos, -1, dir, color, &mut result,upgrade);
 
-                        // console_log!("pawn moves found: {}",result.last().unwrap());
+                        // debug_log!("pawn moves found: {}",result.last().unwrap());
 
                     }
                     
@@ -645,15 +671,17 @@ impl Board{
             new_board.make_possible_move(&mov);
 
             let safe = new_board.check_board_safety()[1-mover.to_num()];
-            // console_log!("cheked board safety for {}: {}",mov,safe);
+            // debug_log!("cheked board safety for {}: {}",mov,safe);
             if ! safe{
                 options.remove(i);
             }else{
                 i += 1;
             }
         }
+        // mover made the last move, the side to move has no answer: mated when in check, else stalemated
         if options.len() == 0{
-            self.state = GameState::Won(mover.other());
+            let in_check = !self.check_board_safety()[1-mover.to_num()];
+            self.state = if in_check {GameState::Won(mover)} else {GameState::Draw};
         }
         options
     }
@@ -675,7 +703,7 @@ impl Board{
         let res = if cp.check_safety(king_pos.x, king_pos.y, king_color){
             cp
         }else{
-            // console_log!("safety_check failed for {} at {}",king_color,king_pos);
+            // debug_log!("safety_check failed for {} at {}",king_color,king_pos);
             *self
         };
         res
@@ -738,6 +766,14 @@ impl Board{
         self.counter += 1;
         let mover:Tile = self.data[start];
 
+        // a pawn can only be taken en passant right after its double move
+        let passed_rank = if last_player_idx == 0 {3} else {4};
+        for num in passed_rank*8..passed_rank*8 + 8{
+            if let Tile::Taken(color,Piece::Pawn,PieceInfo::JustDoubleMoved) = self.data[num]{
+                self.data[num] = Tile::Taken(color,Piece::Pawn,PieceInfo::Moved);
+            }
+        }
+
         let mut lost_value = self.data[end].get_value() as i32;
 
         match self.data[end]{
@@ -812,16 +848,9 @@ impl Board{
         }
     }
 
-    pub fn get_data(&self)-> js_sys::Uint32Array{
-
-        let mut array:[u32;DIM] = [0;DIM];
- 
-        for x in 0..DIM {
-            array[x] = self.data[x].to_num();
-        }
-
-        let r = &array[..];
-        js_sys::Uint32Array::from(r)
+    // the tiles as numbers (see Tile::to_num), the webapp's view of the board
+    pub fn get_data(&self)->Vec<u32>{
+        self.data.iter().map(|tile| tile.to_num()).collect()
     }
 
 }
@@ -853,4 +882,44 @@ impl fmt::Display for Board{
 
         write!(f,"{} \n{}",data_repr,self.counter)
     }
+}
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn state_after_move_generation(fen:&str)->GameState{
+        let mut board = Board::from_fen(fen).unwrap();
+        board.get_legal_moves(5);
+        board.state
+    }
+
+    #[test]
+    fn mate_is_won_by_the_side_that_moved_last(){
+        // fool's mate
+        assert_eq!(state_after_move_generation("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"),GameState::Won(Color::Black));
+        assert_eq!(state_after_move_generation("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"),GameState::Ongoing);
+        assert_eq!(state_after_move_generation("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"),GameState::Won(Color::White));
+    }
+
+    #[test]
+    fn stalemate_is_a_draw(){
+        assert_eq!(state_after_move_generation("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),GameState::Draw);
+        assert_eq!(state_after_move_generation("8/8/8/8/8/1q6/2k5/K7 w - - 0 1"),GameState::Draw);
+    }
+
+    // en passant is only allowed right after the double move
+    #[test]
+    fn en_passant_expires(){
+        let mut board = Board::new();
+        for san in ["e4","Nf6","e5","d5"]{
+            board = board.play_move(&board.parse_move(san).unwrap());
+        }
+        assert!(board.parse_move("exd6").is_ok());
+        assert!(board.to_fen().contains(" d6 "));
+        for san in ["Nc3","Nc6"]{
+            board = board.play_move(&board.parse_move(san).unwrap());
+        }
+        assert!(board.parse_move("exd6").is_err());
+        assert_eq!(board.to_fen(),"r1bqkb1r/ppp1pppp/2n2n2/3pP3/8/2N5/PPPP1PPP/R1BQKBNR w KQkq - 0 4");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+// Where the engine's diagnostic messages go: the browser console in the wasm build, stderr natively.
+// Binaries that own the terminal or speak a protocol on stdout can send them elsewhere or drop them.
+
+use std::sync::RwLock;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+extern "C"{
+    #[wasm_bindgen(js_namespace = console, js_name = log)]
+    fn console_log(s: &str);
+}
+
+#[cfg(feature = "wasm")]
+fn default_logger(s:&str){
+    console_log(s);
+}
+
+#[cfg(not(feature = "wasm"))]
+fn default_logger(s:&str){
+    eprintln!("{}",s);
+}
+
+static LOGGER:RwLock<fn(&str)> = RwLock::new(default_logger);
+
+// replaces the backend, |_| {} silences the engine
+pub fn set_logger(logger:fn(&str)){
+    *LOGGER.write().unwrap() = logger;
+}
+
+// back to the console or stderr
+pub fn reset_logger(){
+    set_logger(default_logger);
+}
+
+pub fn log(s:&str){
+    (LOGGER.read().unwrap())(s);
+}
+
+macro_rules! debug_log {
+    ($($t:tt)*) => ($crate::logger::log(&format_args!($($t)*).to_string()));
+}
+pub(crate) use debug_log;
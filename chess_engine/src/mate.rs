@@ -0,0 +1,150 @@
+// Exhaustive mate search: the attacker tries every move (checks first), every defence has to lose.
+// Depths are iterated upwards so the line found is the shortest mate, and the defender picks the reply
+// that holds out longest.
+//
+// Moves are ordered with what the search learned so far: the move that mated or escaped the last time a
+// position came up goes first, and escapes teach the defender's killers and history.
+
+use std::collections::HashMap;
+
+use crate::*;
+use crate::ordering::{self,MoveKey,Ordering};
+use crate::rng::position_hash;
+use crate::syzygy::is_in_check;
+use crate::tablegen::all_moves;
+
+struct Solver{
+    nodes:u64,
+    limit:u64,
+    ordering:Ordering,
+    // the move that mated or escaped from a position, tried first when it is searched again deeper
+    best:HashMap<u64,MoveKey>,
+}
+
+impl Solver{
+
+    fn exhausted(&self)->bool{
+        self.nodes > self.limit
+    }
+
+    // a line for the side to move mating in at most depth moves, ply counts from the root and previous
+    // is the move that led here
+    fn attack(&mut self,board:&Board,depth:u32,ply:usize,previous:Option<MoveKey>)->Option<Vec<PossibleMove>>{
+        self.nodes += 1;
+        if self.exhausted(){
+            return None;
+        }
+        let attacker = Color::from_num(board.counter as usize % 2);
+        let hash = position_hash(board);
+        let hash_move = self.best.get(&hash).copied();
+        let mut children:Vec<(bool,i32,PossibleMove,Board)> = all_moves(board).into_iter().map(|mov|{
+            let mut child = *board;
+            child.make_possible_move(&mov);
+            let score = self.ordering.score(board,&mov,hash_move,ply,previous);
+            (is_in_check(&child,attacker.other()),score,mov,child)
+        }).collect();
+        // only a check can mate on the last move
+        if depth == 1{
+            children.retain(|(check,_,_,_)| *check);
+        }
+        children.sort_by_key(|(check,score,_,_)| (!check,-score));
+
+        for (_,_,mov,child) in children{
+            if let Some(line) = self.defend(&child,depth,ply + 1,Some(ordering::key(&mov))){
+                self.ordering.cutoff(board,&mov,ply,depth,previous);
+                self.best.insert(hash,ordering::key(&mov));
+                return Some([vec![mov],line].concat());
+            }
+        }
+        None
+    }
+
+    // the longest defence if every reply gets mated within depth - 1 more moves
+    fn defend(&mut self,board:&Board,depth:u32,ply:usize,previous:Option<MoveKey>)->Option<Vec<PossibleMove>>{
+        self.nodes += 1;
+        let mut moves = all_moves(board);
+        if moves.is_empty(){
+            let defender = Color::from_num(board.counter as usize % 2);
+            return if is_in_check(board,defender) {Some(vec![])} else {None};
+        }
+        if depth == 1{
+            return None;
+        }
+        let hash = position_hash(board);
+        self.ordering.sort(board,&mut moves,self.best.get(&hash).copied(),ply,previous);
+        let mut longest:Option<Vec<PossibleMove>> = None;
+        for mov in moves{
+            let mut child = *board;
+            child.make_possible_move(&mov);
+            let Some(line) = (1..depth).find_map(|d| self.attack(&child,d,ply + 1,Some(ordering::key(&mov)))) else{
+                // one escape refutes the attack
+                self.ordering.cutoff(board,&mov,ply,depth,previous);
+                self.best.insert(hash,ordering::key(&mov));
+                return None;
+            };
+            if longest.as_ref().is_none_or(|best| line.len() + 1 > best.len()){
+                longest = Some([vec![mov],line].concat());
+            }
+        }
+        longest
+    }
+}
+
+// gives up (returns None) after visiting about node_limit positions
+pub(crate) fn find_mate_within(board:&Board,moves:u32,node_limit:u64)->Option<Vec<PossibleMove>>{
+    let mut solver = Solver{nodes:0,limit:node_limit,ordering:Ordering::new(),best:HashMap::new()};
+    for depth in 1..=moves{
+        if let Some(line) = solver.attack(board,depth,0,None){
+            return Some(line);
+        }
+        if solver.exhausted(){
+            break;
+        }
+    }
+    None
+}
+
+impl Board{
+
+    // a forced mate for the side to move in at most the given number of its own moves,
+    // the line alternates attacker and defender moves and ends in mate
+    pub fn find_mate(&self,moves:u32)->Option<Vec<PossibleMove>>{
+        find_mate_within(self,moves,u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn mate(fen:&str,moves:u32)->Option<Vec<String>>{
+        let board = Board::from_fen(fen).unwrap();
+        board.find_mate(moves).map(|line| board.line_to_coordinates(&line))
+    }
+
+    #[test]
+    fn mate_in_one(){
+        assert_eq!(mate("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",1).unwrap(),["a1a8"]);
+    }
+
+    #[test]
+    fn mate_in_three(){
+        let board = Board::from_fen("r5rk/5p1p/5R2/4B3/8/8/7P/7K w - - 0 1").unwrap();
+        assert!(board.find_mate(2).is_none());
+        // the shortest mate is found when a longer one is allowed
+        let line = board.find_mate(4).unwrap();
+        assert_eq!(line.len(),5);
+        let mut end = board;
+        for mov in line.iter(){
+            end.make_possible_move(mov);
+        }
+        assert!(all_moves(&end).is_empty() && is_in_check(&end,Color::Black));
+    }
+
+    #[test]
+    fn no_mate(){
+        assert_eq!(mate(Board::start_fen(),2),None);
+        // stalemated, the side to move has nothing to mate with
+        assert_eq!(mate("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",1),None);
+    }
+}
@@ -0,0 +1,86 @@
+// Mobility and threats, both read off the attack map.
+//
+// Mobility counts the squares a knight, bishop, rook or queen attacks that are not taken by its own
+// pieces or covered by enemy pawns. Threats reward attacking pieces with pawns and attacking pieces
+// nobody defends.
+
+use crate::*;
+use crate::attacks::AttackMap;
+use crate::eval::{Collector,Term,Weight};
+
+pub(crate) fn collect(board:&Board,map:&AttackMap,out:&mut impl Collector){
+    let mut own = [0u64;2];
+    for (num,tile) in board.data.iter().enumerate(){
+        if let Tile::Taken(color,_,_) = tile{
+            own[color.to_num()] |= 1 << num;
+        }
+    }
+
+    for (num,tile) in board.data.iter().enumerate(){
+        let Tile::Taken(color,piece,_) = tile else{
+            continue;
+        };
+        let (color,enemy) = (*color,color.other());
+        if matches!(piece,Piece::Knight | Piece::Bishop | Piece::Rook | Piece::Queen){
+            let squares = map.piece_attacks(num) & !own[color.to_num()] & !map.attacked_by_pawns(enemy);
+            out.add(Term::Mobility,Weight::Mobility(*piece),color,squares.count_ones() as i32);
+        }
+        if matches!(piece,Piece::King){
+            continue;
+        }
+        let square = 1 << num;
+        if !matches!(piece,Piece::Pawn) && map.attacked_by_pawns(enemy) & square != 0{
+            out.add(Term::Threats,Weight::PawnThreat,enemy,1);
+        }
+        if map.count(enemy,num) > 0 && map.count(color,num) == 0{
+            out.add(Term::Threats,Weight::Hanging,enemy,1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::eval::Counts;
+
+    fn terms(fen:&str)->Counts{
+        let board = Board::from_fen(fen).unwrap();
+        let mut counts = Counts::default();
+        collect(&board,&AttackMap::new(&board),&mut counts);
+        counts
+    }
+
+    #[test]
+    fn mobility(){
+        assert_eq!(terms("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").get(Weight::Mobility(Piece::Knight),Color::White),2);
+        // c2 is taken by its own pawn and b3 covered by an enemy pawn
+        assert_eq!(terms("4k3/8/8/8/2p5/8/2P5/N3K3 w - - 0 1").get(Weight::Mobility(Piece::Knight),Color::White),0);
+        // up the file and b1 and d1, the pawn on b2 covers c1
+        let counts = terms("4k3/8/8/8/8/8/1p6/R3K3 w - - 0 1");
+        assert_eq!(counts.get(Weight::Mobility(Piece::Rook),Color::White),9);
+        assert_eq!(counts.get(Weight::PawnThreat,Color::Black),1);
+        assert_eq!(counts.get(Weight::Mobility(Piece::Queen),Color::White),0);
+    }
+
+    #[test]
+    fn threats(){
+        // the knight is attacked by a pawn and defended by nobody
+        let counts = terms("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(counts.get(Weight::PawnThreat,Color::White),1);
+        assert_eq!(counts.get(Weight::Hanging,Color::White),1);
+        assert_eq!(counts.get(Weight::Hanging,Color::Black),0);
+        assert_eq!(counts.get(Weight::Mobility(Piece::Knight),Color::Black),8);
+
+        // a defended knight is still threatened by the pawn
+        let counts = terms("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(counts.get(Weight::PawnThreat,Color::White),1);
+        assert_eq!(counts.get(Weight::Hanging,Color::White),0);
+
+        // kings are never threatened, a pawn attacked by a pawn only counts as hanging
+        let counts = terms("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(counts.get(Weight::PawnThreat,Color::White),0);
+        assert_eq!(counts.get(Weight::PawnThreat,Color::Black),0);
+        assert_eq!(counts.get(Weight::Hanging,Color::White),1);
+        assert_eq!(counts.get(Weight::Hanging,Color::Black),1);
+    }
+}
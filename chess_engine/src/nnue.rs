@@ -0,0 +1,263 @@
+// A small efficiently updatable neural network evaluation, used instead of the handcrafted terms when
+// one is loaded.
+//
+// Every (colour, piece, square) seen from one side is an input, 768 in all. They feed a hidden layer
+// per side whose sums, the accumulator, only change where pieces moved, so a child position in the
+// search copies its parent's accumulator and applies the few squares that differ. The hidden values of
+// the side to move and of the other side, clipped to 0..QA, go into a single output.
+//
+// Weights file, all little endian:
+//   "SFNN", version u32 (1), hidden size u32,
+//   feature weights i16 [768][hidden], feature biases i16 [hidden],
+//   output weights i16 [2 * hidden] (side to move first), output bias i32.
+// Feature index is colour * 384 + piece * 64 + square, colour 0 for the side whose view it is, pieces
+// pawn, knight, bishop, rook, queen, king and squares a1 = 0 to h8 = 63, flipped vertically for black.
+// Feature weights and biases are scaled by QA, output weights by QB and the output bias by QA * QB.
+
+use crate::*;
+use crate::score::Score;
+
+const MAGIC:&[u8;4] = b"SFNN";
+const VERSION:u32 = 1;
+const FEATURES:usize = 768;
+const QA:i32 = 255;
+const QB:i32 = 64;
+// the output is a winning chance in logistic units, this turns it into centipawns
+const OUTPUT_SCALE:i32 = 400;
+// keeps a broken file from asking for gigabytes
+const MAX_HIDDEN:usize = 4096;
+
+#[derive(Clone, Debug)]
+pub struct Network{
+    hidden:usize,
+    feature_weights:Vec<i16>,
+    feature_biases:Vec<i16>,
+    output_weights:Vec<i16>,
+    output_bias:i32,
+}
+
+// hidden layer sums for white's and black's view
+#[derive(Clone, Debug)]
+pub(crate) struct Accumulator{
+    values:[Vec<i16>;2],
+}
+
+fn piece_number(piece:Piece)->usize{
+    match piece{
+        Piece::Pawn=>0,
+        Piece::Knight=>1,
+        Piece::Bishop=>2,
+        Piece::Rook=>3,
+        Piece::Queen=>4,
+        Piece::King=>5,
+    }
+}
+
+// input of a piece on a board square (x=0 is the h-file) for the given view
+fn feature(view:Color,color:Color,piece:Piece,num:usize)->usize{
+    let pos = Pos::from_num(num);
+    let square = pos.y as usize * 8 + 7 - pos.x as usize;
+    let (relative,square) = if view == Color::White {(color.to_num(),square)} else {(1 - color.to_num(),square ^ 56)};
+    relative * 384 + piece_number(piece) * 64 + square
+}
+
+fn occupant(tile:&Tile)->Option<(Color,Piece)>{
+    match tile{
+        Tile::Taken(color,piece,_)=>Some((*color,*piece)),
+        Tile::Empty=>None,
+    }
+}
+
+struct Reader<'a>{
+    data:&'a [u8],
+}
+
+impl<'a> Reader<'a>{
+
+    fn take(&mut self,n:usize)->Result<&'a [u8],String>{
+        if self.data.len() < n{
+            return Err("network file is truncated".to_string());
+        }
+        let (head,rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self)->Result<u32,String>{
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self)->Result<i32,String>{
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i16s(&mut self,n:usize)->Result<Vec<i16>,String>{
+        Ok(self.take(2 * n)?.chunks_exact(2).map(|b| i16::from_le_bytes([b[0],b[1]])).collect())
+    }
+}
+
+impl Network{
+
+    pub fn from_bytes(data:&[u8])->Result<Network,String>{
+        let mut reader = Reader{data};
+        if reader.take(4)? != MAGIC{
+            return Err("not a network file".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION{
+            return Err(format!("unsupported network version {}",version));
+        }
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN{
+            return Err(format!("hidden size {} out of range",hidden));
+        }
+        let network = Network{
+            hidden,
+            feature_weights:reader.i16s(FEATURES * hidden)?,
+            feature_biases:reader.i16s(hidden)?,
+            output_weights:reader.i16s(2 * hidden)?,
+            output_bias:reader.i32()?,
+        };
+        if !reader.data.is_empty(){
+            return Err(format!("{} bytes left over after the network",reader.data.len()));
+        }
+        Ok(network)
+    }
+
+    pub fn to_bytes(&self)->Vec<u8>{
+        let mut res = MAGIC.to_vec();
+        res.extend(VERSION.to_le_bytes());
+        res.extend((self.hidden as u32).to_le_bytes());
+        for value in self.feature_weights.iter().chain(self.feature_biases.iter()).chain(self.output_weights.iter()){
+            res.extend(value.to_le_bytes());
+        }
+        res.extend(self.output_bias.to_le_bytes());
+        res
+    }
+
+    pub fn hidden_size(&self)->usize{
+        self.hidden
+    }
+
+    fn add_feature(&self,values:&mut [i16],index:usize,sign:i16){
+        let weights = &self.feature_weights[index * self.hidden..(index + 1) * self.hidden];
+        for (value,weight) in values.iter_mut().zip(weights){
+            *value = value.wrapping_add(sign.wrapping_mul(*weight));
+        }
+    }
+
+    // from scratch, for a position the search did not reach through a parent
+    pub(crate) fn accumulator(&self,board:&Board)->Accumulator{
+        let mut values = [self.feature_biases.clone(),self.feature_biases.clone()];
+        for (num,tile) in board.data.iter().enumerate(){
+            if let Some((color,piece)) = occupant(tile){
+                for view in [Color::White,Color::Black]{
+                    self.add_feature(&mut values[view.to_num()],feature(view,color,piece,num),1);
+                }
+            }
+        }
+        Accumulator{values}
+    }
+
+    // the accumulator of a position one move after the one it was computed for
+    pub(crate) fn update(&self,accumulator:&Accumulator,before:&Board,after:&Board)->Accumulator{
+        let mut values = accumulator.values.clone();
+        for num in 0..64{
+            let (old,new) = (occupant(&before.data[num]),occupant(&after.data[num]));
+            // a piece that only lost its castling or en passant flag
+            let index = |occupant:Option<(Color,Piece)>| occupant.map(|(color,piece)| feature(Color::White,color,piece,num));
+            if index(old) == index(new){
+                continue;
+            }
+            for view in [Color::White,Color::Black]{
+                if let Some((color,piece)) = old{
+                    self.add_feature(&mut values[view.to_num()],feature(view,color,piece,num),-1);
+                }
+                if let Some((color,piece)) = new{
+                    self.add_feature(&mut values[view.to_num()],feature(view,color,piece,num),1);
+                }
+            }
+        }
+        Accumulator{values}
+    }
+
+    // centipawns for the given side
+    pub(crate) fn output(&self,accumulator:&Accumulator,side:Color)->Score{
+        let ours = &accumulator.values[side.to_num()];
+        let theirs = &accumulator.values[side.other().to_num()];
+        let (own_weights,their_weights) = self.output_weights.split_at(self.hidden);
+        let sum = |values:&[i16],weights:&[i16]|->i64{
+            values.iter().zip(weights).map(|(v,w)| (*v as i32).clamp(0,QA) as i64 * *w as i64).sum()
+        };
+        let total = sum(ours,own_weights) + sum(theirs,their_weights) + self.output_bias as i64;
+        Score::centipawns((total * OUTPUT_SCALE as i64 / (QA * QB) as i64) as i32)
+    }
+
+    // positive when white is better
+    pub fn evaluate(&self,board:&Board)->Score{
+        self.output(&self.accumulator(board),Color::White)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Network{
+
+    pub fn load(path:&std::path::Path)->Result<Network,String>{
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}",path.display(),e))?;
+        Network::from_bytes(&data).map_err(|e| format!("{}: {}",path.display(),e))
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::rng::Rng;
+
+    fn random_network(seed:u64,hidden:usize)->Network{
+        let mut rng = Rng::new(seed);
+        let mut values = |n:usize,range:usize| (0..n).map(|_| rng.below(2 * range + 1) as i16 - range as i16).collect::<Vec<i16>>();
+        Network{
+            hidden,
+            feature_weights:values(FEATURES * hidden,100),
+            feature_biases:values(hidden,100),
+            output_weights:values(2 * hidden,64),
+            output_bias:values(1,5000)[0] as i32,
+        }
+    }
+
+    #[test]
+    fn update_matches_accumulator(){
+        let network = random_network(7,16);
+        // en passant, both castlings, promotions and captures
+        for (fen,names) in [
+            ("r3k2r/P7/8/8/3p4/8/4P3/R3K2R w KQkq - 0 1",&["e4","dxe3","O-O","O-O-O","a8=Q+","Kc7","Qxd8+","Kxd8","Rxa7"][..]),
+            ("r3k2r/8/8/8/8/8/6p1/R3K2R b KQkq - 0 1",&["O-O","O-O-O","gxh1=N","Rxh1","Rf2"][..]),
+        ]{
+            let mut board = Board::from_fen(fen).unwrap();
+            let mut accumulator = network.accumulator(&board);
+            for name in names{
+                let mov = board.parse_move(name).unwrap();
+                let next = board.play_move(&mov);
+                accumulator = network.update(&accumulator,&board,&next);
+                board = next;
+                assert_eq!(accumulator.values,network.accumulator(&board).values,"{}",name);
+                assert_eq!(network.output(&accumulator,Color::White),network.evaluate(&board));
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip(){
+        let network = random_network(11,8);
+        let bytes = network.to_bytes();
+        let read = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(read.hidden_size(),8);
+        assert_eq!(read.to_bytes(),bytes);
+        let board = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        assert_eq!(read.evaluate(&board),network.evaluate(&board));
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::from_bytes(&[bytes.as_slice(),&[0]].concat()).is_err());
+        assert!(Network::from_bytes(b"SFNX").is_err());
+    }
+}
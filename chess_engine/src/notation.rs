@@ -0,0 +1,393 @@
+// FEN positions, coordinate move names ("e2e4", "a7a8q") and standard algebraic notation ("Nxf7+").
+//
+// The board stores files mirrored (x=0 is the h-file), so square names are built from 'h' - x.
+// The board keeps no halfmove clock, FEN output always writes 0 there.
+
+use crate::*;
+use crate::syzygy::is_in_check;
+use crate::tablegen::all_moves;
+
+const START_FEN:&str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+pub(crate) fn square_name(num:usize)->String{
+    let pos = Pos::from_num(num);
+    format!("{}{}",(b'h' - pos.x as u8) as char,pos.y + 1)
+}
+
+pub(crate) fn parse_square(name:&str)->Option<usize>{
+    let bytes = name.as_bytes();
+    if bytes.len() != 2 || !(b'a'..=b'h').contains(&bytes[0]) || !(b'1'..=b'8').contains(&bytes[1]){
+        return None;
+    }
+    Some(Pos::from_ints((b'h' - bytes[0]) as i8,(bytes[1] - b'1') as i8).num)
+}
+
+fn upgrade_char(upgrade:u8)->Option<char>{
+    match upgrade{
+        1=>Some('r'),
+        2=>Some('n'),
+        3=>Some('b'),
+        5=>Some('q'),
+        _=>None,
+    }
+}
+
+// what is left of a move name once the parts people write differently are gone: check marks,
+// annotations, capture signs and the '=' of a promotion, "0-0" for castling
+fn normalize_san(text:&str)->String{
+    let mut res:String = text.replace('0',"O").chars().filter(|c| !"+#!?x:=".contains(*c)).collect();
+    if res.ends_with("e.p."){
+        res.truncate(res.len() - 4);
+    }
+    // "e8q" for "e8=Q"
+    if let [.., rank, piece] = res.as_bytes(){
+        if rank.is_ascii_digit() && b"qrbn".contains(piece){
+            let piece = piece.to_ascii_uppercase() as char;
+            res.pop();
+            res.push(piece);
+        }
+    }
+    res
+}
+
+fn piece_fen_char(color:Color,piece:Piece)->char{
+    let c = match piece{
+        Piece::King=>'k',
+        Piece::Queen=>'q',
+        Piece::Rook=>'r',
+        Piece::Bishop=>'b',
+        Piece::Knight=>'n',
+        Piece::Pawn=>'p',
+    };
+    if color == Color::White {c.to_ascii_uppercase()} else {c}
+}
+
+impl PossibleMove{
+
+    // coordinate notation as used by uci, the promotion piece only when the pawn promotes
+    pub fn to_coordinate(&self,board:&Board)->String{
+        let mut res = square_name(self.start.num) + &square_name(self.end.num);
+        let promotes = matches!(board.data[self.start.num],Tile::Taken(_,Piece::Pawn,_)) && (self.end.y == 0 || self.end.y == 7);
+        if promotes{
+            res.extend(upgrade_char(self.upgrade));
+        }
+        res
+    }
+}
+
+impl Board{
+
+    // standard algebraic notation of a legal move on this board
+    pub fn san(&self,mov:&PossibleMove)->String{
+        let Tile::Taken(color,piece,_) = self.data[mov.start.num] else{
+            return mov.to_coordinate(self);
+        };
+        let mut res = String::new();
+        if matches!(piece,Piece::King) && (mov.end.x - mov.start.x).abs() == 2{
+            // the king moves towards the h-file (x=0) when castling short
+            res += if mov.end.x < mov.start.x {"O-O"} else {"O-O-O"};
+        }else{
+            let capture = !self.tile_is_empty(mov.end.num) || !mov.extra.is_empty();
+            if matches!(piece,Piece::Pawn){
+                if capture{
+                    res.push(square_name(mov.start.num).as_bytes()[0] as char);
+                }
+            }else{
+                res.push(piece_fen_char(Color::White,piece));
+                // name the start file or rank when another piece of the kind reaches the same square
+                let rivals:Vec<Pos> = all_moves(self).iter().filter(|other|
+                    other.end.num == mov.end.num && other.start.num != mov.start.num
+                    && matches!(self.data[other.start.num],Tile::Taken(c,p,_) if c == color && std::mem::discriminant(&p) == std::mem::discriminant(&piece))
+                ).map(|other| other.start).collect();
+                let start = square_name(mov.start.num);
+                if !rivals.is_empty(){
+                    if rivals.iter().all(|other| other.x != mov.start.x){
+                        res.push(start.as_bytes()[0] as char);
+                    }else if rivals.iter().all(|other| other.y != mov.start.y){
+                        res.push(start.as_bytes()[1] as char);
+                    }else{
+                        res += &start;
+                    }
+                }
+            }
+            if capture{
+                res.push('x');
+            }
+            res += &square_name(mov.end.num);
+            if matches!(piece,Piece::Pawn) && (mov.end.y == 0 || mov.end.y == 7){
+                res.push('=');
+                res.extend(upgrade_char(mov.upgrade).map(|c| c.to_ascii_uppercase()));
+            }
+        }
+
+        let mut next = *self;
+        next.make_possible_move(mov);
+        if is_in_check(&next,color.other()){
+            res.push(if all_moves(&next).is_empty() {'#'} else {'+'});
+        }
+        res
+    }
+
+    pub fn start_fen()->&'static str{
+        START_FEN
+    }
+
+    pub fn from_fen(fen:&str)->Result<Board,String>{
+        let fields:Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 2{
+            return Err(format!("fen needs at least placement and side to move: {}",fen));
+        }
+        let castling = fields.get(2).copied().unwrap_or("-");
+
+        let mut nums = [0u32;64];
+        let ranks:Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8{
+            return Err(format!("fen needs 8 ranks, found {}",ranks.len()));
+        }
+        for (r,rank) in ranks.iter().enumerate(){
+            let y = 7 - r;
+            let mut file = 0;
+            for c in rank.chars(){
+                if let Some(skip) = c.to_digit(10){
+                    file += skip as usize;
+                    continue;
+                }
+                let num = match c.to_ascii_lowercase(){
+                    'r'=>1,
+                    'n'=>2,
+                    'b'=>3,
+                    'k'=>4,
+                    'q'=>5,
+                    'p'=>6,
+                    _=>return Err(format!("unknown piece '{}'",c)),
+                } + if c.is_ascii_lowercase() {6} else {0};
+                if file > 7{
+                    return Err(format!("rank {} is too long",8 - r));
+                }
+                nums[y * 8 + 7 - file] = num;
+                file += 1;
+            }
+            if file != 8{
+                return Err(format!("rank {} does not have 8 files",8 - r));
+            }
+        }
+
+        let mut board = Board::from_nums(nums);
+        for color in [Color::White,Color::Black]{
+            let kings = board.data.iter().filter(|tile| matches!(tile,Tile::Taken(c,Piece::King,_) if *c == color)).count();
+            if kings != 1{
+                return Err(format!("{} needs exactly one king",color));
+            }
+        }
+
+        // pieces only keep their "unmoved" state where it still means something
+        for num in 0..64{
+            if let Tile::Taken(color,piece,_) = board.data[num]{
+                let pos = Pos::from_num(num);
+                let home = if color == Color::White {0} else {7};
+                let (king_side,queen_side) = if color == Color::White {('K','Q')} else {('k','q')};
+                let unmoved = match piece{
+                    Piece::Pawn=>pos.y == home + color.get_dir(),
+                    Piece::King=>pos.num == Pos::from_ints(3,home).num && (castling.contains(king_side) || castling.contains(queen_side)),
+                    Piece::Rook=>(pos.num == Pos::from_ints(0,home).num && castling.contains(king_side))
+                        || (pos.num == Pos::from_ints(7,home).num && castling.contains(queen_side)),
+                    _=>false,
+                };
+                let info = if unmoved {PieceInfo::None} else {PieceInfo::Moved};
+                board.data[num] = Tile::Taken(color,piece,info);
+            }
+        }
+
+        let black_to_move = match fields[1]{
+            "w"=>false,
+            "b"=>true,
+            other=>return Err(format!("unknown side to move '{}'",other)),
+        };
+
+        // the pawn that can be taken en passant stands just behind the target square
+        if let Some(target) = fields.get(3).filter(|field| **field != "-"){
+            let name = target;
+            let target = parse_square(target).ok_or(format!("bad en passant square '{}'",target))?;
+            // behind a pawn that just moved two squares: the sixth rank with white to move, the third with black
+            if target / 8 != if black_to_move {2} else {5}{
+                return Err(format!("en passant square '{}' is on the wrong rank",name));
+            }
+            let pawn = if black_to_move {target + 8} else {target - 8};
+            if let Tile::Taken(color,Piece::Pawn,_) = board.data[pawn]{
+                board.data[pawn] = Tile::Taken(color,Piece::Pawn,PieceInfo::JustDoubleMoved);
+            }
+        }
+
+        let fullmove:i32 = fields.get(5).and_then(|field| field.parse().ok()).unwrap_or(1);
+        board.counter = 2 * (fullmove.max(1) - 1) + black_to_move as i32;
+        Ok(board)
+    }
+
+    pub fn white_to_move(&self)->bool{
+        self.counter % 2 == 0
+    }
+
+    // as in FEN, 1 for the first move of the game
+    pub fn fullmove_number(&self)->u32{
+        self.counter as u32 / 2 + 1
+    }
+
+    pub fn in_check(&self)->bool{
+        is_in_check(self,Color::from_num(self.counter as usize % 2))
+    }
+
+    // every legal move of the side to move, a promotion once per piece
+    pub fn legal_moves(&self)->Vec<PossibleMove>{
+        all_moves(self)
+    }
+
+    // the position after a legal move in coordinate notation ("e2e4", "e1g1", "e7e8q")
+    pub fn play_coordinate(&self,name:&str)->Result<Board,String>{
+        let mov = all_moves(self).into_iter().find(|mov| mov.to_coordinate(self) == name).ok_or_else(|| format!("illegal move {}",name))?;
+        Ok(self.play_move(&mov))
+    }
+
+    // a legal move in coordinate notation or SAN, forgiving about check marks, capture signs and "0-0"
+    pub fn parse_move(&self,text:&str)->Result<PossibleMove,String>{
+        let moves = all_moves(self);
+        if let Some(mov) = moves.iter().find(|mov| mov.to_coordinate(self) == text.to_lowercase()){
+            return Ok(mov.clone());
+        }
+        let name = normalize_san(text);
+        let mut found = moves.into_iter().filter(|mov| normalize_san(&self.san(mov)) == name);
+        match (found.next(),found.next()){
+            (Some(mov),None)=>Ok(mov),
+            (Some(_),Some(_))=>Err(format!("ambiguous move {}",text)),
+            _=>Err(format!("illegal move {}",text)),
+        }
+    }
+
+    // the position after a move from legal_moves or parse_move
+    pub fn play_move(&self,mov:&PossibleMove)->Board{
+        let mut next = *self;
+        next.make_possible_move(mov);
+        next
+    }
+
+    // the moves of a line in coordinate notation, each read on the board it is played on
+    pub fn line_to_coordinates(&self,line:&[PossibleMove])->Vec<String>{
+        let mut board = *self;
+        line.iter().map(|mov|{
+            let name = mov.to_coordinate(&board);
+            board.make_possible_move(mov);
+            name
+        }).collect()
+    }
+
+    pub fn to_fen(&self)->String{
+        let mut placement = String::new();
+        for y in (0..8).rev(){
+            let mut empty = 0;
+            for x in (0..8).rev(){
+                match self.data[y * 8 + x]{
+                    Tile::Empty=>empty += 1,
+                    Tile::Taken(color,piece,_)=>{
+                        if empty > 0{
+                            placement += &empty.to_string();
+                            empty = 0;
+                        }
+                        placement.push(piece_fen_char(color,piece));
+                    }
+                }
+            }
+            if empty > 0{
+                placement += &empty.to_string();
+            }
+            if y > 0{
+                placement.push('/');
+            }
+        }
+
+        let mut castling = String::new();
+        for color in [Color::White,Color::Black]{
+            let home = if color == Color::White {0} else {7};
+            if !matches!(self.data[Pos::from_ints(3,home).num],Tile::Taken(c,Piece::King,PieceInfo::None) if c == color){
+                continue;
+            }
+            for (x,side) in [(0,Piece::King),(7,Piece::Queen)]{
+                if matches!(self.data[Pos::from_ints(x,home).num],Tile::Taken(c,Piece::Rook,PieceInfo::None) if c == color){
+                    castling.push(piece_fen_char(color,side));
+                }
+            }
+        }
+        if castling.is_empty(){
+            castling.push('-');
+        }
+
+        let mover = Color::from_num(self.counter as usize % 2);
+        let passant_rank = if mover == Color::White {4} else {3};
+        let passant = (0..8).map(|x| Pos::from_ints(x,passant_rank)).find(|pos|
+            matches!(self.data[pos.num],Tile::Taken(c,Piece::Pawn,PieceInfo::JustDoubleMoved) if c != mover)
+        ).map(|pos| square_name(pos.step(0,mover.get_dir()).unwrap().num)).unwrap_or("-".to_string());
+
+        format!("{} {} {} {} 0 {}",placement,if mover == Color::White {"w"} else {"b"},castling,passant,self.counter / 2 + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn san_line(fen:&str,names:&[&str])->Vec<String>{
+        let mut board = Board::from_fen(fen).unwrap();
+        names.iter().map(|name|{
+            let mov = board.parse_move(name).unwrap();
+            let san = board.san(&mov);
+            board = board.play_move(&mov);
+            san
+        }).collect()
+    }
+
+    #[test]
+    fn fen_round_trip(){
+        for fen in [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 2",
+            "8/8/8/4k3/8/8/8/3QK3 b - - 0 40",
+        ]{
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(),fen);
+        }
+    }
+
+    #[test]
+    fn en_passant_square_on_the_wrong_rank(){
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - e1 0 1").is_err());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - a8 0 1").is_err());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - e3 0 1").is_err());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - z9 0 1").is_err());
+    }
+
+    #[test]
+    fn san(){
+        assert_eq!(san_line(START_FEN,&["e4","e5","Nf3","Nc6","Bb5","Nf6","O-O"]),["e4","e5","Nf3","Nc6","Bb5","Nf6","O-O"]);
+        assert_eq!(san_line(START_FEN,&["f3","e5","g4","Qh4"]),["f3","e5","g4","Qh4#"]);
+        // en passant and the other castle
+        assert_eq!(san_line("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",&["exf6"]),["exf6"]);
+        assert_eq!(san_line("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",&["O-O-O","O-O"]),["O-O-O","O-O"]);
+        // promotion with check and the start file or rank when two pieces reach the same square
+        assert_eq!(san_line("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1",&["b8=Q"]),["b8=Q+"]);
+        assert_eq!(san_line("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1",&["Nbd2"]),["Nbd2"]);
+        assert_eq!(san_line("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1",&["R1a3"]),["R1a3"]);
+    }
+
+    #[test]
+    fn parse_move_variants(){
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let coordinate = |text:&str| board.parse_move(text).map(|mov| mov.to_coordinate(&board));
+        assert_eq!(coordinate("0-0").unwrap(),"e1g1");
+        assert_eq!(coordinate("O-O-O").unwrap(),"e1c1");
+        assert_eq!(coordinate("exd6e.p.").unwrap(),"e5d6");
+        assert_eq!(coordinate("ed6").unwrap(),"e5d6");
+        assert_eq!(coordinate("bxa8=N").unwrap(),"b7a8n");
+        assert_eq!(coordinate("b8q").unwrap(),"b7b8q");
+        assert_eq!(coordinate("b7b8r").unwrap(),"b7b8r");
+        assert!(coordinate("e5e7").is_err());
+    }
+}
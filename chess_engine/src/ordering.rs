@@ -0,0 +1,202 @@
+// Move ordering for the searches: the best move known for the position first, then captures that do
+// not lose material by SEE (most valuable victim first, least valuable attacker among equals), then
+// promotions, the killer moves of the ply, the countermove to the opponent's last move, the remaining
+// quiet moves by history and captures that lose material last.
+//
+// Killers, history and countermoves are learned from the moves that cut a search short, so they only
+// help searches that report those. MCTS expands every move eventually and uses the static part.
+
+use crate::*;
+use crate::see;
+use crate::syzygy::is_capture;
+use crate::tablegen::is_promotion;
+
+// start square, end square and promotion, what identifies a move across positions
+pub(crate) type MoveKey = (usize,usize,u8);
+
+const HASH_MOVE:i32 = 1 << 30;
+const GOOD_CAPTURE:i32 = 1 << 28;
+const PROMOTION:i32 = 1 << 27;
+const KILLER:i32 = 1 << 26;
+const COUNTERMOVE:i32 = 1 << 25;
+const BAD_CAPTURE:i32 = -(1 << 28);
+// history is halved once a score reaches this, so old cutoffs fade and it stays below the tiers above
+const HISTORY_MAX:i32 = 1 << 20;
+
+pub(crate) fn key(mov:&PossibleMove)->MoveKey{
+    (mov.start.num,mov.end.num,mov.upgrade)
+}
+
+fn is_quiet(board:&Board,mov:&PossibleMove)->bool{
+    !is_capture(board,mov) && !is_promotion(board,mov)
+}
+
+// the part of the order that needs nothing learned: captures and promotions ahead of quiet moves,
+// quiet moves that leave a piece to be taken and captures that lose material behind them
+pub(crate) fn static_score(board:&Board,mov:&PossibleMove)->i32{
+    if is_capture(board,mov){
+        let victim = match board.data[mov.end.num]{
+            Tile::Taken(_,piece,_)=>see::value(piece),
+            Tile::Empty=>see::value(Piece::Pawn),
+        };
+        let attacker = match board.data[mov.start.num]{
+            Tile::Taken(_,piece,_)=>see::value(piece),
+            Tile::Empty=>0,
+        };
+        let mvv_lva = victim * 16 - attacker / 16;
+        let gain = board.see(mov);
+        return if gain >= 0 {GOOD_CAPTURE + mvv_lva} else {BAD_CAPTURE + gain};
+    }
+    if is_promotion(board,mov){
+        return PROMOTION + see::value(see::promotion(mov.upgrade));
+    }
+    board.see(mov).min(0)
+}
+
+// in place, best first
+pub(crate) fn sort_static(board:&Board,moves:&mut [PossibleMove]){
+    moves.sort_by_cached_key(|mov| -static_score(board,mov));
+}
+
+// what one search learned about quiet moves
+pub(crate) struct Ordering{
+    // two per ply, the newest first
+    killers:Vec<[Option<MoveKey>;2]>,
+    // per colour, from and to square
+    history:Vec<[[i32;64];64]>,
+    // per start and end square of the opponent's last move
+    countermoves:Vec<[Option<MoveKey>;64]>,
+}
+
+impl Ordering{
+
+    pub(crate) fn new()->Ordering{
+        Ordering{killers:vec![],history:vec![[[0;64];64];2],countermoves:vec![[None;64];64]}
+    }
+
+    // previous is the move that led to the position
+    pub(crate) fn score(&self,board:&Board,mov:&PossibleMove,hash_move:Option<MoveKey>,ply:usize,previous:Option<MoveKey>)->i32{
+        let key = key(mov);
+        if hash_move == Some(key){
+            return HASH_MOVE;
+        }
+        if !is_quiet(board,mov){
+            return static_score(board,mov);
+        }
+        if let Some(slot) = self.killers.get(ply).and_then(|killers| killers.iter().position(|killer| *killer == Some(key))){
+            return KILLER - slot as i32;
+        }
+        if previous.is_some_and(|(start,end,_)| self.countermoves[start][end] == Some(key)){
+            return COUNTERMOVE;
+        }
+        self.history[board.counter as usize % 2][mov.start.num][mov.end.num]
+    }
+
+    // in place, best first
+    pub(crate) fn sort(&self,board:&Board,moves:&mut [PossibleMove],hash_move:Option<MoveKey>,ply:usize,previous:Option<MoveKey>){
+        moves.sort_by_cached_key(|mov| -self.score(board,mov,hash_move,ply,previous));
+    }
+
+    // mov made the search at this ply and remaining depth stop early
+    pub(crate) fn cutoff(&mut self,board:&Board,mov:&PossibleMove,ply:usize,depth:u32,previous:Option<MoveKey>){
+        if !is_quiet(board,mov){
+            return;
+        }
+        let key = key(mov);
+        if self.killers.len() <= ply{
+            self.killers.resize(ply + 1,[None;2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(key){
+            killers[1] = killers[0];
+            killers[0] = Some(key);
+        }
+
+        let history = &mut self.history[board.counter as usize % 2];
+        let entry = &mut history[mov.start.num][mov.end.num];
+        *entry += (depth * depth) as i32;
+        if *entry >= HISTORY_MAX{
+            for row in history.iter_mut(){
+                for value in row.iter_mut(){
+                    *value /= 2;
+                }
+            }
+        }
+
+        if let Some((start,end,_)) = previous{
+            self.countermoves[start][end] = Some(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn legal(board:&Board)->Vec<PossibleMove>{
+        let mut board = *board;
+        board.get_legal_moves(5)
+    }
+
+    fn find(board:&Board,moves:&[PossibleMove],san:&str)->MoveKey{
+        key(moves.iter().find(|mov| board.san(mov) == san).unwrap())
+    }
+
+    #[test]
+    fn static_order(){
+        // the queen before the defended pawn, a rook walking into a pawn's capture last
+        let board = Board::from_fen("4k3/8/2p5/1p1q4/2P1N3/8/8/R3K3 w - - 0 1").unwrap();
+        let mut moves = legal(&board);
+        sort_static(&board,&mut moves);
+        let order:Vec<String> = moves.iter().map(|mov| board.san(mov)).collect();
+        assert_eq!(order[..2],["cxd5","cxb5"]);
+        assert_eq!(order.last().unwrap(),"Ra4");
+        assert!(order.iter().position(|san| san == "Nd6+") > order.iter().position(|san| san == "Nc3"));
+    }
+
+    #[test]
+    fn learned_order(){
+        let board = Board::new();
+        let moves = legal(&board);
+        let mut ordering = Ordering::new();
+        for san in ["Nf3","e4"]{
+            let mov = moves.iter().find(|mov| board.san(mov) == san).unwrap();
+            ordering.cutoff(&board,mov,0,3,None);
+        }
+        let d4 = moves.iter().find(|mov| board.san(mov) == "d4").unwrap();
+        ordering.cutoff(&board,d4,1,5,None);
+
+        let mut sorted = moves.clone();
+        ordering.sort(&board,&mut sorted,Some(find(&board,&moves,"c4")),0,None);
+        let order:Vec<String> = sorted.iter().map(|mov| board.san(mov)).collect();
+        assert_eq!(order[..4],["c4","e4","Nf3","d4"]);
+        // history is kept per side to move
+        let black = board.play_move(d4);
+        assert!(legal(&black).iter().all(|mov| ordering.score(&black,mov,None,5,None) == 0));
+    }
+
+    #[test]
+    fn countermoves_and_history(){
+        let board = Board::new();
+        let moves = legal(&board);
+        let a3 = moves.iter().find(|mov| board.san(mov) == "a3").unwrap();
+        let mut ordering = Ordering::new();
+        ordering.cutoff(&board,a3,2,1,Some((50,34,0)));
+        assert_eq!(ordering.score(&board,a3,None,2,None),KILLER);
+        assert_eq!(ordering.score(&board,a3,None,0,Some((50,34,0))),COUNTERMOVE);
+        assert_eq!(ordering.score(&board,a3,None,0,None),1);
+
+        // a full entry halves the whole table
+        ordering.cutoff(&board,a3,3,1024,None);
+        assert_eq!(ordering.score(&board,a3,None,0,None),(HISTORY_MAX + 1) / 2);
+
+        // captures are ordered by material and teach nothing
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let moves = legal(&board);
+        let capture = moves.iter().find(|mov| board.san(mov) == "exd5").unwrap();
+        let mut ordering = Ordering::new();
+        ordering.cutoff(&board,capture,0,4,None);
+        assert_eq!(ordering.score(&board,capture,None,0,None),static_score(&board,capture));
+        assert!(ordering.killers.is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+// Everything the evaluation and the search weigh positions by, in one place so engine personalities
+// can be tried without recompiling. The defaults are PeSTO's values and tables, with hand-picked pawn
+// structure weights.
+//
+// Parameter files are JSON or TOML with the same field names. Fields left out keep their default, but
+// a field that is given must be complete: all six pieces, 64 squares for every table and 8 ranks for the
+// pawn terms by rank.
+
+use serde::{Deserialize,Serialize};
+
+use crate::*;
+use crate::eval::Weight;
+
+// value added to the phase by each piece, 24 with all pieces on the board
+const DEFAULT_PHASE_INC:[i32;6] = [0,1,1,2,4,0];
+
+const DEFAULT_MG_VALUE:[i32;6] = [82,337,365,477,1025,0];
+const DEFAULT_EG_VALUE:[i32;6] = [94,281,297,512,936,0];
+
+const DEFAULT_MG_TABLE:[[i32;64];6] = [
+    // pawn
+    [
+          0,   0,   0,   0,   0,   0,  0,   0,
+         98, 134,  61,  95,  68, 126, 34, -11,
+         -6,   7,  26,  31,  65,  56, 25, -20,
+        -14,  13,   6,  21,  23,  12, 17, -23,
+        -27,  -2,  -5,  12,  17,   6, 10, -25,
+        -26,  -4,  -4, -10,   3,   3, 33, -12,
+        -35,  -1, -20, -23, -15,  24, 38, -22,
+          0,   0,   0,   0,   0,   0,  0,   0,
+    ],
+    // knight
+    [
+        -167, -89, -34, -49,  61, -97, -15, -107,
+         -73, -41,  72,  36,  23,  62,   7,  -17,
+         -47,  60,  37,  65,  84, 129,  73,   44,
+          -9,  17,  19,  53,  37,  69,  18,   22,
+         -13,   4,  16,  13,  28,  19,  21,   -8,
+         -23,  -9,  12,  10,  19,  17,  25,  -16,
+         -29, -53, -12,  -3,  -1,  18, -14,  -19,
+        -105, -21, -58, -33, -17, -28, -19,  -23,
+    ],
+    // bishop
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    // rook
+    [
+         32,  42,  32,  51, 63,  9,  31,  43,
+         27,  32,  58,  62, 80, 67,  26,  44,
+         -5,  19,  26,  36, 17, 45,  61,  16,
+        -24, -11,   7,  26, 24, 35,  -8, -20,
+        -36, -26, -12,  -1,  9, -7,   6, -23,
+        -45, -25, -16, -17,  3,  0,  -5, -33,
+        -44, -16, -20,  -9, -1, 11,  -6, -71,
+        -19, -13,   1,  17, 16,  7, -37, -26,
+    ],
+    // queen
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    // king
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+const DEFAULT_EG_TABLE:[[i32;64];6] = [
+    // pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // knight
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    // bishop
+    [
+        -14, -21, -11,  -8, -7,  -9, -17, -24,
+         -8,  -4,   7, -12, -3, -13,  -4, -14,
+          2,  -8,   0,  -1, -2,   6,   0,   4,
+         -3,   9,  12,   9, 14,  10,   3,   2,
+         -6,   3,  13,  19,  7,  10,  -3,  -9,
+        -12,  -3,   8,  10, 13,   3,  -7, -15,
+        -14, -18,  -7,  -1,  4,  -9, -15, -27,
+        -23,  -9, -23,  -5, -9, -16,  -5, -17,
+    ],
+    // rook
+    [
+        13, 10, 18, 15, 12,  12,   8,   5,
+        11, 13, 13, 11, -3,   3,   8,   3,
+         7,  7,  7,  5,  4,  -3,  -5,  -3,
+         4,  3, 13,  1,  2,   1,  -1,   2,
+         3,  5,  8,  4, -5,  -6,  -8, -11,
+        -4,  0, -5, -1, -7, -12,  -8, -16,
+        -6, -6,  0,  2, -9,  -9, -11,  -3,
+        -9,  2,  3, -1, -5, -13,   4, -20,
+    ],
+    // queen
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    // king
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+// the exploration weight the search was tuned with
+const DEFAULT_EXPLORATION:f64 = 0.03;
+
+// pawn structure, by rank from the pawn's own side where it applies
+const DEFAULT_PASSED:[(i32,i32);8] = [(0,0),(0,5),(5,10),(10,20),(20,40),(40,70),(70,110),(0,0)];
+const DEFAULT_CONNECTED:[(i32,i32);8] = [(0,0),(3,2),(5,4),(8,6),(15,12),(25,20),(40,30),(0,0)];
+const DEFAULT_DOUBLED:(i32,i32) = (-10,-25);
+const DEFAULT_ISOLATED:(i32,i32) = (-8,-12);
+const DEFAULT_BACKWARD:(i32,i32) = (-8,-6);
+const DEFAULT_PASSED_OWN_KING:(i32,i32) = (0,-3);
+const DEFAULT_PASSED_ENEMY_KING:(i32,i32) = (0,6);
+
+// king safety counts in the midgame, king activity in the endgame
+const DEFAULT_SHIELD_CLOSE:(i32,i32) = (12,0);
+const DEFAULT_SHIELD_FAR:(i32,i32) = (6,0);
+const DEFAULT_KING_OPEN_FILE:(i32,i32) = (-25,0);
+const DEFAULT_KING_HALF_OPEN_FILE:(i32,i32) = (-12,0);
+const DEFAULT_KING_ATTACK:[(i32,i32);6] = [(-3,0),(-6,0),(-5,0),(-8,0),(-12,0),(0,0)];
+const DEFAULT_KING_PAWN_DISTANCE:(i32,i32) = (0,-6);
+
+const DEFAULT_MOBILITY:[(i32,i32);6] = [(0,0),(4,4),(5,5),(2,4),(1,2),(0,0)];
+const DEFAULT_PAWN_THREAT:(i32,i32) = (30,20);
+const DEFAULT_HANGING:(i32,i32) = (20,15);
+
+// one value per piece type, written out by name in parameter files
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PieceMap<T>{
+    pub pawn:T,
+    pub knight:T,
+    pub bishop:T,
+    pub rook:T,
+    pub queen:T,
+    pub king:T,
+}
+
+impl<T> PieceMap<T>{
+
+    fn from_array(values:[T;6])->PieceMap<T>{
+        let [pawn,knight,bishop,rook,queen,king] = values;
+        PieceMap{pawn,knight,bishop,rook,queen,king}
+    }
+
+    pub(crate) fn get(&self,piece:Piece)->&T{
+        match piece{
+            Piece::Pawn=>&self.pawn,
+            Piece::Knight=>&self.knight,
+            Piece::Bishop=>&self.bishop,
+            Piece::Rook=>&self.rook,
+            Piece::Queen=>&self.queen,
+            Piece::King=>&self.king,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self,piece:Piece)->&mut T{
+        match piece{
+            Piece::Pawn=>&mut self.pawn,
+            Piece::Knight=>&mut self.knight,
+            Piece::Bishop=>&mut self.bishop,
+            Piece::Rook=>&mut self.rook,
+            Piece::Queen=>&mut self.queen,
+            Piece::King=>&mut self.king,
+        }
+    }
+
+    fn iter(&self)->impl Iterator<Item=(&'static str,&T)>{
+        [("pawn",&self.pawn),("knight",&self.knight),("bishop",&self.bishop),("rook",&self.rook),("queen",&self.queen),("king",&self.king)].into_iter()
+    }
+}
+
+// a midgame and an endgame value
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct Pair{
+    pub mg:i32,
+    pub eg:i32,
+}
+
+impl Pair{
+    const fn new((mg,eg):(i32,i32))->Pair{
+        Pair{mg,eg}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PawnParams{
+    // 8 entries by rank from the pawn's side, 0 for its first rank
+    pub passed:Vec<Pair>,
+    // for a pawn with a friendly pawn beside it or defending it
+    pub connected:Vec<Pair>,
+    // for every pawn behind another one of its colour on the same file
+    pub doubled:Pair,
+    pub isolated:Pair,
+    // a pawn that can not be defended by pawns and whose advance is stopped by an enemy pawn
+    pub backward:Pair,
+    // per square between a king and a passed pawn's stop square, times how far the pawn is past its third rank
+    pub passed_own_king:Pair,
+    pub passed_enemy_king:Pair,
+}
+
+impl Default for PawnParams{
+    fn default()->PawnParams{
+        PawnParams{
+            passed:DEFAULT_PASSED.map(Pair::new).to_vec(),
+            connected:DEFAULT_CONNECTED.map(Pair::new).to_vec(),
+            doubled:Pair::new(DEFAULT_DOUBLED),
+            isolated:Pair::new(DEFAULT_ISOLATED),
+            backward:Pair::new(DEFAULT_BACKWARD),
+            passed_own_king:Pair::new(DEFAULT_PASSED_OWN_KING),
+            passed_enemy_king:Pair::new(DEFAULT_PASSED_ENEMY_KING),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KingParams{
+    // per own pawn one and two ranks in front of the king, on its file or next to it
+    pub shield_close:Pair,
+    pub shield_far:Pair,
+    // per file at or next to the king without own pawns, open when it has no pawns at all
+    pub open_file:Pair,
+    pub half_open_file:Pair,
+    // per square around the king an enemy piece attacks, by the attacking piece
+    pub attack:PieceMap<Pair>,
+    // per square between the king and the nearest pawn
+    pub pawn_distance:Pair,
+}
+
+impl Default for KingParams{
+    fn default()->KingParams{
+        KingParams{
+            shield_close:Pair::new(DEFAULT_SHIELD_CLOSE),
+            shield_far:Pair::new(DEFAULT_SHIELD_FAR),
+            open_file:Pair::new(DEFAULT_KING_OPEN_FILE),
+            half_open_file:Pair::new(DEFAULT_KING_HALF_OPEN_FILE),
+            attack:PieceMap::from_array(DEFAULT_KING_ATTACK.map(Pair::new)),
+            pawn_distance:Pair::new(DEFAULT_KING_PAWN_DISTANCE),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThreatParams{
+    // per enemy knight, bishop, rook or queen attacked by a pawn
+    pub pawn:Pair,
+    // per enemy piece or pawn attacked and not defended
+    pub hanging:Pair,
+}
+
+impl Default for ThreatParams{
+    fn default()->ThreatParams{
+        ThreatParams{pawn:Pair::new(DEFAULT_PAWN_THREAT),hanging:Pair::new(DEFAULT_HANGING)}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalParams{
+    pub mg_value:PieceMap<i32>,
+    pub eg_value:PieceMap<i32>,
+    // 64 squares from white's side with a8 first, black reads them mirrored
+    pub mg_table:PieceMap<Vec<i32>>,
+    pub eg_table:PieceMap<Vec<i32>>,
+    // value added to the game phase by each piece on the board
+    pub phase_inc:PieceMap<i32>,
+    pub pawns:PawnParams,
+    pub king:KingParams,
+    // per square a piece attacks that is neither its own nor covered by enemy pawns
+    pub mobility:PieceMap<Pair>,
+    pub threats:ThreatParams,
+    // how much the search looks at moves it knows little about, rather than the best one so far
+    pub exploration:f64,
+}
+
+impl Default for EvalParams{
+    fn default()->EvalParams{
+        EvalParams{
+            mg_value:PieceMap::from_array(DEFAULT_MG_VALUE),
+            eg_value:PieceMap::from_array(DEFAULT_EG_VALUE),
+            mg_table:PieceMap::from_array(DEFAULT_MG_TABLE.map(Vec::from)),
+            eg_table:PieceMap::from_array(DEFAULT_EG_TABLE.map(Vec::from)),
+            phase_inc:PieceMap::from_array(DEFAULT_PHASE_INC),
+            pawns:PawnParams::default(),
+            king:KingParams::default(),
+            mobility:PieceMap::from_array(DEFAULT_MOBILITY.map(Pair::new)),
+            threats:ThreatParams::default(),
+            exploration:DEFAULT_EXPLORATION,
+        }
+    }
+}
+
+impl EvalParams{
+
+    pub fn from_json(text:&str)->Result<EvalParams,String>{
+        serde_json::from_str::<EvalParams>(text).map_err(|e| e.to_string())?.checked()
+    }
+
+    pub fn to_json(&self)->String{
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // the phase of the starting position, where the evaluation is all midgame
+    pub fn max_phase(&self)->i32{
+        let p = &self.phase_inc;
+        2 * (8 * p.pawn + 2 * p.knight + 2 * p.bishop + 2 * p.rook + p.queen + p.king)
+    }
+
+    // the values a term of the evaluation is read from
+    pub(crate) fn weight(&self,weight:Weight)->Pair{
+        match weight{
+            Weight::Value(piece)=>Pair{mg:*self.mg_value.get(piece),eg:*self.eg_value.get(piece)},
+            Weight::Square(piece,sq)=>Pair{mg:self.mg_table.get(piece)[sq],eg:self.eg_table.get(piece)[sq]},
+            Weight::Passed(rank)=>self.pawns.passed[rank],
+            Weight::Connected(rank)=>self.pawns.connected[rank],
+            Weight::Doubled=>self.pawns.doubled,
+            Weight::Isolated=>self.pawns.isolated,
+            Weight::Backward=>self.pawns.backward,
+            Weight::PassedOwnKing=>self.pawns.passed_own_king,
+            Weight::PassedEnemyKing=>self.pawns.passed_enemy_king,
+            Weight::ShieldClose=>self.king.shield_close,
+            Weight::ShieldFar=>self.king.shield_far,
+            Weight::KingOpenFile=>self.king.open_file,
+            Weight::KingHalfOpenFile=>self.king.half_open_file,
+            Weight::KingAttack(piece)=>*self.king.attack.get(piece),
+            Weight::KingPawnDistance=>self.king.pawn_distance,
+            Weight::Mobility(piece)=>*self.mobility.get(piece),
+            Weight::PawnThreat=>self.threats.pawn,
+            Weight::Hanging=>self.threats.hanging,
+        }
+    }
+
+    pub(crate) fn set_weight(&mut self,weight:Weight,pair:Pair){
+        match weight{
+            Weight::Value(piece)=>{
+                *self.mg_value.get_mut(piece) = pair.mg;
+                *self.eg_value.get_mut(piece) = pair.eg;
+            }
+            Weight::Square(piece,sq)=>{
+                self.mg_table.get_mut(piece)[sq] = pair.mg;
+                self.eg_table.get_mut(piece)[sq] = pair.eg;
+            }
+            Weight::Passed(rank)=>self.pawns.passed[rank] = pair,
+            Weight::Connected(rank)=>self.pawns.connected[rank] = pair,
+            Weight::Doubled=>self.pawns.doubled = pair,
+            Weight::Isolated=>self.pawns.isolated = pair,
+            Weight::Backward=>self.pawns.backward = pair,
+            Weight::PassedOwnKing=>self.pawns.passed_own_king = pair,
+            Weight::PassedEnemyKing=>self.pawns.passed_enemy_king = pair,
+            Weight::ShieldClose=>self.king.shield_close = pair,
+            Weight::ShieldFar=>self.king.shield_far = pair,
+            Weight::KingOpenFile=>self.king.open_file = pair,
+            Weight::KingHalfOpenFile=>self.king.half_open_file = pair,
+            Weight::KingAttack(piece)=>*self.king.attack.get_mut(piece) = pair,
+            Weight::KingPawnDistance=>self.king.pawn_distance = pair,
+            Weight::Mobility(piece)=>*self.mobility.get_mut(piece) = pair,
+            Weight::PawnThreat=>self.threats.pawn = pair,
+            Weight::Hanging=>self.threats.hanging = pair,
+        }
+    }
+
+    fn checked(self)->Result<EvalParams,String>{
+        for (name,tables) in [("mg_table",&self.mg_table),("eg_table",&self.eg_table)]{
+            if let Some((piece,table)) = tables.iter().find(|(_,table)| table.len() != 64){
+                return Err(format!("{}.{} has {} squares instead of 64",name,piece,table.len()));
+            }
+        }
+        for (name,ranks) in [("pawns.passed",&self.pawns.passed),("pawns.connected",&self.pawns.connected)]{
+            if ranks.len() != 8{
+                return Err(format!("{} has {} ranks instead of 8",name,ranks.len()));
+            }
+        }
+        if self.phase_inc.iter().any(|(_,inc)| *inc < 0) || self.max_phase() <= 0{
+            return Err("phase_inc must not be negative and must not be all zero".to_string());
+        }
+        if !(self.exploration >= 0. && self.exploration.is_finite()){
+            return Err(format!("exploration {} is not a finite non-negative number",self.exploration));
+        }
+        Ok(self)
+    }
+}
+
+// TOML only comes up with files, the webapp exchanges JSON and does not carry the parser
+#[cfg(not(target_arch = "wasm32"))]
+impl EvalParams{
+
+    pub fn from_toml(text:&str)->Result<EvalParams,String>{
+        toml::from_str::<EvalParams>(text).map_err(|e| e.to_string())?.checked()
+    }
+
+    pub fn to_toml(&self)->String{
+        toml::to_string(self).unwrap()
+    }
+
+    // TOML for files ending in .toml, JSON otherwise
+    pub fn load(path:&std::path::Path)->Result<EvalParams,String>{
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}",path.display(),e))?;
+        let params = if path.extension().is_some_and(|ext| ext == "toml") {EvalParams::from_toml(&text)} else {EvalParams::from_json(&text)};
+        params.map_err(|e| format!("{}: {}",path.display(),e))
+    }
+}
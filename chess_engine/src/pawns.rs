@@ -0,0 +1,280 @@
+// Pawn structure: passed, connected, doubled, isolated and backward pawns, plus how close the kings are
+// to the passed pawns in the endgame.
+//
+// Everything but the king distances depends on the pawns alone, so those terms are cached in a table
+// keyed on the placement of the pawns. Pawns move rarely compared to pieces, and most positions in a
+// search share their pawn structure with many others.
+//
+// Pawns are kept as bitboards with the same numbering as the board, bit y*8+x with x=0 the h-file.
+
+use crate::*;
+use crate::eval::{Collector,Term,Totals,Weight};
+use crate::params::EvalParams;
+
+const FILE_H:u64 = 0x0101_0101_0101_0101;
+// slots, just under a megabyte
+const TABLE_SIZE:usize = 1 << 14;
+
+pub(crate) fn file_mask(x:usize)->u64{
+    FILE_H << x
+}
+
+fn adjacent_files(x:usize)->u64{
+    let left = if x > 0 {file_mask(x - 1)} else {0};
+    let right = if x < 7 {file_mask(x + 1)} else {0};
+    left | right
+}
+
+// the ranks in front of rank y as seen by color
+fn ahead(color:Color,y:usize)->u64{
+    match color{
+        Color::White if y == 7=>0,
+        Color::White=>!0 << ((y + 1) * 8),
+        Color::Black=>(1 << (y * 8)) - 1,
+    }
+}
+
+fn rank_mask(y:usize)->u64{
+    0xff << (y * 8)
+}
+
+// rank counted from the pawn's own side, 0 for its first rank
+fn relative_rank(color:Color,y:usize)->usize{
+    if color == Color::White {y} else {7 - y}
+}
+
+pub(crate) fn distance(a:usize,b:usize)->i32{
+    let (a,b) = (Pos::from_num(a),Pos::from_num(b));
+    ((a.x - b.x).abs()).max((a.y - b.y).abs()) as i32
+}
+
+fn squares(mut bits:u64)->impl Iterator<Item=usize>{
+    std::iter::from_fn(move ||{
+        if bits == 0{
+            return None;
+        }
+        let num = bits.trailing_zeros() as usize;
+        bits &= bits - 1;
+        Some(num)
+    })
+}
+
+// the pawns of both colours, white first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Pawns{
+    bits:[u64;2],
+}
+
+impl Pawns{
+
+    pub(crate) fn new(board:&Board)->Pawns{
+        let mut bits = [0;2];
+        for (num,tile) in board.data.iter().enumerate(){
+            if let Tile::Taken(color,Piece::Pawn,_) = tile{
+                bits[color.to_num()] |= 1 << num;
+            }
+        }
+        Pawns{bits}
+    }
+
+    pub(crate) fn bits(&self,color:Color)->u64{
+        self.bits[color.to_num()]
+    }
+
+    fn is_passed(&self,color:Color,num:usize)->bool{
+        let (x,y) = (num % 8,num / 8);
+        let front = ahead(color,y);
+        self.bits[color.other().to_num()] & front & (file_mask(x) | adjacent_files(x)) == 0
+            && self.bits[color.to_num()] & front & file_mask(x) == 0
+    }
+
+    pub(crate) fn passed(&self,color:Color)->u64{
+        squares(self.bits[color.to_num()]).filter(|num| self.is_passed(color,*num)).fold(0,|bits,num| bits | 1 << num)
+    }
+
+    // every term that only depends on the pawns
+    pub(crate) fn collect_structure(&self,out:&mut impl Collector){
+        for color in [Color::White,Color::Black]{
+            let (own,enemy) = (self.bits[color.to_num()],self.bits[color.other().to_num()]);
+            for num in squares(own){
+                let (x,y) = (num % 8,num / 8);
+                let rank = relative_rank(color,y);
+                let neighbours = own & adjacent_files(x);
+
+                if self.is_passed(color,num){
+                    out.add(Term::PassedPawns,Weight::Passed(rank),color,1);
+                }
+                if own & ahead(color,y) & file_mask(x) != 0{
+                    out.add(Term::PawnStructure,Weight::Doubled,color,1);
+                }
+                if neighbours == 0{
+                    out.add(Term::PawnStructure,Weight::Isolated,color,1);
+                    continue;
+                }
+
+                let behind = if color == Color::White {rank_mask(y.saturating_sub(1))} else {rank_mask((y + 1).min(7))};
+                if neighbours & (rank_mask(y) | behind) != 0{
+                    out.add(Term::PawnStructure,Weight::Connected(rank),color,1);
+                }
+
+                // no pawn beside or behind can ever defend it, and an enemy pawn guards the square in front
+                let stop_y = if color == Color::White {y + 1} else {y.wrapping_sub(1)};
+                if stop_y < 8 && neighbours & !ahead(color,y) == 0{
+                    let guard_y = if color == Color::White {stop_y + 1} else {stop_y.wrapping_sub(1)};
+                    if guard_y < 8 && enemy & adjacent_files(x) & rank_mask(guard_y) != 0{
+                        out.add(Term::PawnStructure,Weight::Backward,color,1);
+                    }
+                }
+            }
+        }
+    }
+
+    // kings near the square in front of a passed pawn, counted more the further it advanced
+    pub(crate) fn collect_king_proximity(board:&Board,passed:[u64;2],out:&mut impl Collector){
+        let kings = [Color::White,Color::Black].map(|color| board.data.iter().position(|tile| matches!(tile,Tile::Taken(c,Piece::King,_) if *c == color)));
+        for color in [Color::White,Color::Black]{
+            let (Some(own_king),Some(enemy_king)) = (kings[color.to_num()],kings[color.other().to_num()]) else{
+                continue;
+            };
+            for num in squares(passed[color.to_num()]){
+                let (x,y) = (num % 8,num / 8);
+                let advance = relative_rank(color,y) as i32 - 2;
+                if advance <= 0{
+                    continue;
+                }
+                let stop = if color == Color::White {(y + 1) * 8 + x} else {(y - 1) * 8 + x};
+                out.add(Term::PassedPawns,Weight::PassedOwnKing,color,distance(own_king,stop) * advance);
+                out.add(Term::PassedPawns,Weight::PassedEnemyKing,color,distance(enemy_king,stop) * advance);
+            }
+        }
+    }
+}
+
+// the pawn-only terms summed per colour, with the passed pawns for the king distances
+#[derive(Clone, Copy, Debug)]
+struct Entry{
+    pawns:Pawns,
+    mg:[i32;2],
+    eg:[i32;2],
+    passed:[u64;2],
+}
+
+// results for pawn structures seen before, a newer structure replaces an older one in the same slot
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PawnTable{
+    entries:Vec<Option<Entry>>,
+}
+
+impl PawnTable{
+
+    // the weights are part of the result, so the table must be cleared when they change
+    pub(crate) fn clear(&mut self){
+        self.entries.clear();
+    }
+
+    // midgame and endgame sums per colour of the cached terms, and the passed pawns
+    pub(crate) fn probe(&mut self,pawns:&Pawns,params:&EvalParams)->([i32;2],[i32;2],[u64;2]){
+        if self.entries.is_empty(){
+            self.entries = vec![None;TABLE_SIZE];
+        }
+        let hash = (pawns.bits[0] ^ pawns.bits[1].rotate_left(32)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let slot = (hash >> 50) as usize % TABLE_SIZE;
+        match self.entries[slot]{
+            Some(entry) if entry.pawns == *pawns=>(entry.mg,entry.eg,entry.passed),
+            _=>{
+                let mut totals = Totals::new(params);
+                pawns.collect_structure(&mut totals);
+                let passed = [pawns.passed(Color::White),pawns.passed(Color::Black)];
+                self.entries[slot] = Some(Entry{pawns:*pawns,mg:totals.mg,eg:totals.eg,passed});
+                (totals.mg,totals.eg,passed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::eval::Counts;
+    use crate::notation::parse_square;
+
+    fn structure(fen:&str)->(Pawns,Counts){
+        let board = Board::from_fen(fen).unwrap();
+        let pawns = Pawns::new(&board);
+        let mut counts = Counts::default();
+        pawns.collect_structure(&mut counts);
+        Pawns::collect_king_proximity(&board,[pawns.passed(Color::White),pawns.passed(Color::Black)],&mut counts);
+        (pawns,counts)
+    }
+
+    fn bits(names:&[&str])->u64{
+        names.iter().fold(0,|bits,name| bits | 1 << parse_square(name).unwrap())
+    }
+
+    #[test]
+    fn isolated_and_doubled(){
+        let (pawns,counts) = structure("4k3/8/8/8/8/4P3/P2PP3/4K3 w - - 0 1");
+        assert_eq!(pawns.passed(Color::White),bits(&["a2","d2","e3"]));
+        assert_eq!(counts.get(Weight::Isolated,Color::White),1);
+        assert_eq!(counts.get(Weight::Doubled,Color::White),1);
+        // d2 and e2 side by side, e3 with d2 behind it
+        assert_eq!(counts.get(Weight::Connected(1),Color::White),2);
+        assert_eq!(counts.get(Weight::Connected(2),Color::White),1);
+        assert_eq!(counts.get(Weight::Passed(1),Color::White),2);
+        assert_eq!(counts.get(Weight::Passed(2),Color::White),1);
+        assert_eq!(counts.get(Weight::Backward,Color::White),0);
+    }
+
+    #[test]
+    fn backward_and_passed(){
+        // d3 cannot be defended and c5 guards d4, e4 is passed with d3 behind it
+        let (pawns,counts) = structure("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(pawns.passed(Color::White),bits(&["e4"]));
+        assert_eq!(pawns.passed(Color::Black),0);
+        assert_eq!(counts.get(Weight::Backward,Color::White),1);
+        assert_eq!(counts.get(Weight::Connected(3),Color::White),1);
+        assert_eq!(counts.get(Weight::Connected(2),Color::White),0);
+        assert_eq!(counts.get(Weight::Passed(3),Color::White),1);
+        assert_eq!(counts.get(Weight::Isolated,Color::Black),1);
+        assert_eq!(counts.get(Weight::Isolated,Color::White),0);
+
+        // the same for black with the board turned around
+        let (pawns,counts) = structure("4k3/8/3p4/4p3/2P5/8/8/4K3 b - - 0 1");
+        assert_eq!(pawns.passed(Color::Black),bits(&["e5"]));
+        assert_eq!(counts.get(Weight::Backward,Color::Black),1);
+        assert_eq!(counts.get(Weight::Connected(3),Color::Black),1);
+        assert_eq!(counts.get(Weight::Passed(3),Color::Black),1);
+        assert_eq!(counts.get(Weight::Isolated,Color::White),1);
+    }
+
+    #[test]
+    fn king_proximity(){
+        // d6 is the stop square, the pawn is two ranks past the third
+        let (_,counts) = structure("7k/8/8/3P4/8/8/8/K7 w - - 0 1");
+        assert_eq!(counts.get(Weight::PassedOwnKing,Color::White),5 * 2);
+        assert_eq!(counts.get(Weight::PassedEnemyKing,Color::White),4 * 2);
+        // not counted before the pawn advanced
+        let (_,counts) = structure("7k/8/8/8/8/3P4/8/K7 w - - 0 1");
+        assert_eq!(counts.get(Weight::PassedOwnKing,Color::White),0);
+    }
+
+    #[test]
+    fn table(){
+        let params = EvalParams::default();
+        let mut table = PawnTable::default();
+        let fens = ["rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2","4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1"];
+        for _ in 0..2{
+            for fen in fens{
+                let pawns = Pawns::new(&Board::from_fen(fen).unwrap());
+                let mut totals = Totals::new(&params);
+                pawns.collect_structure(&mut totals);
+                let fresh = (totals.mg,totals.eg,[pawns.passed(Color::White),pawns.passed(Color::Black)]);
+                assert_eq!(table.probe(&pawns,&params),fresh);
+                assert_eq!(table.probe(&pawns,&params),fresh);
+            }
+        }
+        assert_eq!(table.entries.iter().filter(|entry| entry.is_some()).count(),2);
+        table.clear();
+        assert!(table.entries.is_empty());
+    }
+}
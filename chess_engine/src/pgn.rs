@@ -0,0 +1,205 @@
+// Reading and writing games as PGN. Only the main line of the first game is read: comments, variations,
+// NAGs and move numbers are skipped, the moves may be in SAN or coordinate notation. A FEN tag sets the
+// start position.
+
+use crate::*;
+use crate::tablegen::all_moves;
+
+// "1-0", "0-1" or "1/2-1/2" once the side to move has no legal move, "*" before
+pub fn game_result(board:&Board)->&'static str{
+    if !all_moves(board).is_empty(){
+        "*"
+    }else if !board.in_check(){
+        "1/2-1/2"
+    }else if board.white_to_move(){
+        "0-1"
+    }else{
+        "1-0"
+    }
+}
+
+// joins movetext tokens into lines of at most 80 columns
+pub(crate) fn wrap(tokens:Vec<String>)->String{
+    let mut res = String::new();
+    let mut line = String::new();
+    for token in tokens{
+        if !line.is_empty() && line.len() + token.len() + 1 > 80{
+            res += &line;
+            res.push('\n');
+            line.clear();
+        }
+        if !line.is_empty(){
+            line.push(' ');
+        }
+        line += &token;
+    }
+    res += &line;
+    res.push('\n');
+    res
+}
+
+// the game from start, tags like ("White","Alice") come first and the result is filled in
+pub fn write_pgn(start:&Board,moves:&[PossibleMove],tags:&[(&str,&str)])->String{
+    let mut board = *start;
+    let mut text = vec![];
+    for (i,mov) in moves.iter().enumerate(){
+        if board.white_to_move(){
+            text.push(format!("{}.",board.fullmove_number()));
+        }else if i == 0{
+            text.push(format!("{}...",board.fullmove_number()));
+        }
+        text.push(board.san(mov));
+        board = board.play_move(mov);
+    }
+    let result = game_result(&board);
+    text.push(result.to_string());
+
+    let mut res = String::new();
+    for (name,value) in tags{
+        res += &format!("[{} \"{}\"]\n",name,value.replace('\\',"\\\\").replace('"',"\\\""));
+    }
+    res += &format!("[Result \"{}\"]\n",result);
+    if start.to_fen() != Board::start_fen(){
+        res += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n",start.to_fen());
+    }
+    res.push('\n');
+    res + &wrap(text)
+}
+
+// the start position and the moves of the first game in text
+pub fn read_pgn(text:&str)->Result<(Board,Vec<PossibleMove>),String>{
+    let mut start = Board::new();
+    let mut movetext = String::new();
+    let mut in_movetext = false;
+    for line in text.lines(){
+        let line = line.trim();
+        // "%" escapes a line
+        if line.starts_with('%'){
+            continue;
+        }
+        if line.starts_with('[') && !in_movetext{
+            let tag = line.trim_start_matches('[').trim_end_matches(']');
+            if let Some((name,value)) = tag.split_once(' '){
+                if name == "FEN"{
+                    start = Board::from_fen(value.trim().trim_matches('"'))?;
+                }
+            }
+            continue;
+        }
+        // tags after the movetext belong to the next game
+        if line.starts_with('['){
+            break;
+        }
+        in_movetext |= !line.is_empty();
+        movetext += line;
+        movetext.push('\n');
+    }
+
+    // comments run to "}" or the end of the line, variations nest
+    let mut plain = String::new();
+    let mut depth = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next(){
+        match c{
+            '{'=>{
+                chars.by_ref().find(|c| *c == '}');
+                plain.push(' ');
+            }
+            ';'=>{
+                chars.by_ref().find(|c| *c == '\n');
+                plain.push(' ');
+            }
+            '('=>depth += 1,
+            ')'=>{
+                depth -= 1;
+                plain.push(' ');
+            }
+            c if depth == 0=>plain.push(c),
+            _=>{}
+        }
+    }
+
+    let mut board = start;
+    let mut moves = vec![];
+    for token in plain.split_whitespace(){
+        if ["1-0","0-1","1/2-1/2","*"].contains(&token){
+            break;
+        }
+        // "12.", "12..." and "12.e4"
+        let name = token.trim_start_matches(|c:char| c.is_ascii_digit()).trim_start_matches('.');
+        let name = if name.len() < token.len() && !token.contains('.') {token} else {name};
+        if name.is_empty() || name.starts_with('$'){
+            continue;
+        }
+        let mov = board.parse_move(name).map_err(|e| format!("move {}: {}",board.fullmove_number(),e))?;
+        board = board.play_move(&mov);
+        moves.push(mov);
+    }
+    Ok((start,moves))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn sans(start:&Board,moves:&[PossibleMove])->Vec<String>{
+        let mut board = *start;
+        moves.iter().map(|mov|{
+            let san = board.san(mov);
+            board = board.play_move(mov);
+            san
+        }).collect()
+    }
+
+    #[test]
+    fn write_and_read(){
+        let start = Board::new();
+        let mut board = start;
+        let mut moves = vec![];
+        for san in ["f3","e5","g4","Qh4#"]{
+            let mov = board.parse_move(san).unwrap();
+            board = board.play_move(&mov);
+            moves.push(mov);
+        }
+        let text = write_pgn(&start,&moves,&[("White","Al \"the\" Fool"),("Black","B\\C")]);
+        assert_eq!(text,"[White \"Al \\\"the\\\" Fool\"]\n[Black \"B\\\\C\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n");
+
+        let (read_start,read) = read_pgn(&text).unwrap();
+        assert_eq!(read_start.to_fen(),Board::start_fen());
+        assert_eq!(sans(&read_start,&read),["f3","e5","g4","Qh4#"]);
+    }
+
+    #[test]
+    fn fen_tag(){
+        let fen = "4k3/8/8/8/8/8/4p3/4K3 b - - 0 40";
+        let start = Board::from_fen(fen).unwrap();
+        let moves = vec![start.parse_move("Kd7").unwrap()];
+        let text = write_pgn(&start,&moves,&[]);
+        assert_eq!(text,format!("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"{}\"]\n\n40... Kd7 *\n",fen));
+
+        let (read_start,read) = read_pgn(&text).unwrap();
+        assert_eq!(read_start.to_fen(),fen);
+        assert_eq!(sans(&read_start,&read),["Kd7"]);
+    }
+
+    #[test]
+    fn movetext(){
+        let text = "% exported by hand\n[Event \"Casual\"]\n\n1.e4 {best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. g1f3 $1 ; to the end\n\
+            2... Nc6 3.Bb5 a6 $6 1/2-1/2\n\n[Event \"Next\"]\n\n1. d4 *\n";
+        let (start,moves) = read_pgn(text).unwrap();
+        assert_eq!(start.to_fen(),Board::start_fen());
+        assert_eq!(sans(&start,&moves),["e4","e5","Nf3","Nc6","Bb5","a6"]);
+
+        let err = read_pgn("1. e4 e5 2. Ke3 *").err().unwrap();
+        assert!(err.starts_with("move 2:"),"{}",err);
+    }
+
+    #[test]
+    fn long_games_wrap(){
+        let tokens:Vec<String> = (1..=40).map(|i| format!("{}.",i)).collect();
+        let text = wrap(tokens);
+        assert!(text.lines().all(|line| line.len() <= 80));
+        assert_eq!(text.split_whitespace().count(),40);
+        assert!(text.ends_with("40.\n"));
+    }
+}
@@ -0,0 +1,109 @@
+// Grades a played move against the engine's choice in the same position.
+//
+// Both moves are scored by a search from the position after them, so the comparison does not depend on
+// how many visits the played move got in the engine's own tree. Scores are for the player who moved.
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::*;
+use crate::bot::{self,SearchLimits};
+use crate::eval::Evaluator;
+use crate::score::Score;
+use crate::rng::Rng;
+use crate::syzygy::{Tablebase,is_in_check};
+use crate::tablegen::all_moves;
+
+// largest centipawn loss for each grade, anything above is a blunder
+const GOOD_LOSS:i32 = 40;
+const INACCURACY_LOSS:i32 = 90;
+const MISTAKE_LOSS:i32 = 200;
+// mates count as this many centipawns when computing the loss
+const SCORE_CAP:i32 = 2000;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveQuality{
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality{
+
+    pub fn from_loss(loss:i32)->MoveQuality{
+        match loss{
+            l if l <= GOOD_LOSS=>MoveQuality::Good,
+            l if l <= INACCURACY_LOSS=>MoveQuality::Inaccuracy,
+            l if l <= MISTAKE_LOSS=>MoveQuality::Mistake,
+            _=>MoveQuality::Blunder,
+        }
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[derive(Clone, Debug)]
+pub struct MoveReview{
+    pub quality:MoveQuality,
+    // centipawns the played move gives away compared to the engine's move
+    pub loss:i32,
+    pub played_score:Score,
+    pub best_score:Score,
+    pub san:String,
+    // the better alternative, equal to the played move when that was best
+    pub best_san:String,
+    pub best_start:usize,
+    pub best_end:usize,
+    pub best_upgrade:u8,
+}
+
+// the played move as generated by the board, None if it is not legal
+pub(crate) fn find_move(board:&Board,start:usize,end:usize,upgrade:u8)->Option<PossibleMove>{
+    let moves:Vec<PossibleMove> = all_moves(board).into_iter().filter(|mov| mov.start.num == start && mov.end.num == end).collect();
+    // the upgrade only tells promotions apart
+    moves.iter().find(|mov| mov.upgrade == upgrade).or(moves.first()).cloned()
+}
+
+// score for the player who just moved into this position
+fn score_after(board:&Board,tablebase:&Tablebase,evaluator:&Evaluator,limits:&SearchLimits,rng:&mut Rng)->Score{
+    let to_move = Color::from_num(board.counter as usize % 2);
+    if all_moves(board).is_empty(){
+        return if is_in_check(board,to_move) {Score::mated_in(0).parent()} else {Score::ZERO};
+    }
+    bot::choose_move(*board,tablebase,evaluator,&limits.strength(),rng,None).score.parent()
+}
+
+pub(crate) fn review_move(board:&Board,played:&PossibleMove,tablebase:&Tablebase,evaluator:&Evaluator,limits:&SearchLimits,rng:&mut Rng)->MoveReview{
+    let best = bot::choose_move(*board,tablebase,evaluator,&limits.strength(),rng,None).mov;
+    let same = best.start.num == played.start.num && best.end.num == played.end.num && best.upgrade == played.upgrade;
+
+    let mut after = *board;
+    after.make_possible_move(played);
+    let played_score = score_after(&after,tablebase,evaluator,limits,rng);
+    let best_score = if same{
+        played_score
+    }else{
+        let mut after = *board;
+        after.make_possible_move(&best);
+        score_after(&after,tablebase,evaluator,limits,rng)
+    };
+
+    // the engine's move scored no better than the played one, so the played move counts as best
+    let (best,best_score) = if best_score <= played_score {(played.clone(),played_score)} else {(best,best_score)};
+    let loss = best_score.capped_centipawns(SCORE_CAP) - played_score.capped_centipawns(SCORE_CAP);
+    let quality = if loss == 0 {MoveQuality::Best} else {MoveQuality::from_loss(loss)};
+
+    MoveReview{
+        quality,
+        loss,
+        played_score,
+        best_score,
+        san:board.san(played),
+        best_san:board.san(&best),
+        best_start:best.start.num,
+        best_end:best.end.num,
+        best_upgrade:best.upgrade,
+    }
+}
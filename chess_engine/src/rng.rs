@@ -0,0 +1,101 @@
+// small seedable prng (splitmix64), so games can be replayed from a seed
+// and the wasm build never needs os entropy
+
+use crate::Board;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rng{
+    state:u64,
+}
+
+impl Rng{
+
+    pub fn new(seed:u64)->Rng{
+        Rng{state:seed}
+    }
+
+    // seeded from the time (native) or Math.random (wasm)
+    pub fn from_entropy()->Rng{
+        Rng::new(entropy())
+    }
+
+    pub fn next_u64(&mut self)->u64{
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0,1)
+    pub fn next_f32(&mut self)->f32{
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // uniform in 0..n
+    pub fn below(&mut self,n:usize)->usize{
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+// the pieces, their flags and the move counter
+pub(crate) fn position_hash(board:&Board)->u64{
+    let mut hash:u64 = 0xcbf29ce484222325;
+    for tile in board.data.iter(){
+        hash = (hash ^ tile.to_num() as u64).wrapping_mul(0x100000001b3);
+    }
+    (hash ^ board.counter as u64).wrapping_mul(0x100000001b3)
+}
+
+// mixes the seed with the position, so the same seed and position always give the same stream
+pub(crate) fn position_seed(seed:u64,board:&Board)->u64{
+    Rng::new(seed ^ position_hash(board)).next_u64()
+}
+
+#[cfg(feature = "wasm")]
+fn entropy()->u64{
+    (js_sys::Math::random() * u64::MAX as f64) as u64
+}
+
+#[cfg(not(feature = "wasm"))]
+fn entropy()->u64{
+    use std::time::{SystemTime,UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::bot::{self,Difficulty};
+    use crate::eval::Evaluator;
+    use crate::syzygy::Tablebase;
+
+    #[test]
+    fn same_seed_same_move(){
+        let board = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let (tablebase,evaluator) = (Tablebase::new(),Evaluator::default());
+        // the beginner level plays with noise, so only the seed makes it repeat
+        let strength = Difficulty::Beginner.strength();
+        for seed in 0..4{
+            let moves:Vec<String> = (0..2).map(|_|{
+                let mut rng = Rng::new(position_seed(seed,&board));
+                format!("{:?}",bot::choose_move(board,&tablebase,&evaluator,&strength,&mut rng,None).mov)
+            }).collect();
+            assert_eq!(moves[0],moves[1]);
+        }
+    }
+
+    #[test]
+    fn seeds_per_position(){
+        let start = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let after = start.play_move(&start.parse_move("e4").unwrap());
+        assert_eq!(position_seed(1,&start),position_seed(1,&start));
+        assert_ne!(position_seed(1,&start),position_seed(1,&after));
+        assert_ne!(position_seed(1,&start),position_seed(2,&start));
+
+        // the move counter is part of the position
+        let white = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(position_seed(7,&white),position_seed(7,&black));
+    }
+}
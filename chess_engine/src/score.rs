@@ -0,0 +1,130 @@
+// Scores in centipawns from the point of view of the side to move, with mates encoded like most
+// engines do: MATE minus the plies to mate, negative when getting mated.
+//
+// The search itself averages expected results between -1 and 1. The two scales meet through the
+// winning chance: a score is worth 2 * win_probability - 1 to the search.
+
+use std::fmt;
+use std::ops::Neg;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+// centipawns that move the winning chance by one logistic unit, lichess uses about the same curve
+const LOGISTIC_SCALE:f32 = 275.;
+const MATE:i32 = 32000;
+// anything closer to MATE than this is a mate score
+const MAX_MATE_PLIES:i32 = 1000;
+// tablebase wins rank below every mate and above every evaluation
+const TABLEBASE_WIN:i32 = 20000;
+// largest evaluation the search scale can express
+const MAX_CENTIPAWNS:i32 = 10000;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Score{
+    value:i32,
+}
+
+impl Score{
+
+    pub const ZERO:Score = Score{value:0};
+
+    pub fn centipawns(centipawns:i32)->Score{
+        Score{value:centipawns.clamp(-MAX_CENTIPAWNS,MAX_CENTIPAWNS)}
+    }
+
+    // the side to move mates in this many plies
+    pub fn mate_in(plies:u32)->Score{
+        Score{value:MATE - plies as i32}
+    }
+
+    // the side to move gets mated in this many plies, 0 when it is mated already
+    pub fn mated_in(plies:u32)->Score{
+        Score{value:-MATE + plies as i32}
+    }
+
+    // a tablebase result with the distance to zeroing or mate, positive when winning
+    pub fn tablebase(distance:i32)->Score{
+        match distance{
+            0=>Score::ZERO,
+            d if d > 0=>Score{value:TABLEBASE_WIN - d.min(MAX_MATE_PLIES)},
+            d=>Score{value:-TABLEBASE_WIN - d.max(-MAX_MATE_PLIES)},
+        }
+    }
+
+    // from an expected result of the search
+    pub fn from_value(value:f32)->Score{
+        let p = ((value.clamp(-1.,1.) + 1.) / 2.).clamp(1e-6,1. - 1e-6);
+        Score::centipawns((LOGISTIC_SCALE * (p / (1. - p)).ln()).round() as i32)
+    }
+
+    pub fn is_mate(&self)->bool{
+        self.value.abs() > MATE - MAX_MATE_PLIES
+    }
+
+    // the same position seen from the other side, one ply further from a mate
+    pub fn parent(self)->Score{
+        if self.is_mate(){
+            Score{value:-self.value + self.value.signum()}
+        }else{
+            -self
+        }
+    }
+
+    // expected result between -1 and 1 for the search
+    pub fn to_value(&self)->f32{
+        2. * self.win_probability() - 1.
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Score{
+
+    // raw value, mates close to +-32000
+    pub fn raw(&self)->i32{
+        self.value
+    }
+
+    // full moves to mate, negative when getting mated
+    pub fn mate_moves(&self)->Option<i32>{
+        if !self.is_mate(){
+            return None;
+        }
+        let plies = MATE - self.value.abs();
+        Some(self.value.signum() * (plies + 1) / 2)
+    }
+
+    // centipawns with mates capped, for averages and losses
+    pub fn capped_centipawns(&self,cap:i32)->i32{
+        self.value.clamp(-cap,cap)
+    }
+
+    pub fn win_probability(&self)->f32{
+        if self.is_mate(){
+            return if self.value > 0 {1.} else {0.};
+        }
+        1. / (1. + (-(self.value as f32) / LOGISTIC_SCALE).exp())
+    }
+
+    // "+1.30", "-0.45" or "#4", "#-3"
+    pub fn text(&self)->String{
+        self.to_string()
+    }
+}
+
+impl Neg for Score{
+    type Output = Score;
+    fn neg(self)->Score{
+        Score{value:-self.value}
+    }
+}
+
+impl fmt::Display for Score{
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result{
+        match self.mate_moves(){
+            Some(moves)=>write!(f,"#{}",moves),
+            None=>write!(f,"{:+.2}",self.value as f32 / 100.),
+        }
+    }
+}
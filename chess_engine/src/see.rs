@@ -0,0 +1,225 @@
+// Static exchange evaluation: what a move wins or loses in material once both sides have taken back
+// on its target square as long as that pays, each time with their least valuable attacker.
+//
+// Attackers are looked up from the target square with the pieces that already took out of the
+// occupancy, so a rook behind a rook or a queen behind a bishop joins in once the one in front is gone.
+// Pins and checks are ignored like everywhere else the attacks are used.
+
+use crate::*;
+use crate::attacks;
+
+// values in centipawns, the king's only matters as the last piece that would be taken
+const VALUES:[i32;6] = [100,320,330,500,900,20000];
+const PIECES:[Piece;6] = [Piece::Pawn,Piece::Knight,Piece::Bishop,Piece::Rook,Piece::Queen,Piece::King];
+
+pub(crate) fn value(piece:Piece)->i32{
+    VALUES[index(piece)]
+}
+
+fn index(piece:Piece)->usize{
+    match piece{
+        Piece::Pawn=>0,
+        Piece::Knight=>1,
+        Piece::Bishop=>2,
+        Piece::Rook=>3,
+        Piece::Queen=>4,
+        Piece::King=>5,
+    }
+}
+
+pub(crate) fn promotion(upgrade:u8)->Piece{
+    match upgrade{
+        1=>Piece::Rook,
+        2=>Piece::Knight,
+        3=>Piece::Bishop,
+        _=>Piece::Queen,
+    }
+}
+
+// the pieces of each colour and kind, white first
+struct Pieces{
+    bits:[[u64;6];2],
+}
+
+impl Pieces{
+
+    fn new(board:&Board)->Pieces{
+        let mut bits = [[0;6];2];
+        for (num,tile) in board.data.iter().enumerate(){
+            if let Tile::Taken(color,piece,_) = tile{
+                bits[color.to_num()][index(*piece)] |= 1 << num;
+            }
+        }
+        Pieces{bits}
+    }
+
+    // pieces of either colour still in occupied that attack the square
+    fn attackers(&self,num:usize,occupied:u64)->u64{
+        let both = |piece:Piece| self.bits[0][index(piece)] | self.bits[1][index(piece)];
+        let diagonal = both(Piece::Bishop) | both(Piece::Queen);
+        let straight = both(Piece::Rook) | both(Piece::Queen);
+        // a pawn attacks the square from where a pawn of the other colour on it would attack
+        let bits = attacks::attacks(Piece::Pawn,Color::Black,num,0) & self.bits[0][0]
+            | attacks::attacks(Piece::Pawn,Color::White,num,0) & self.bits[1][0]
+            | attacks::attacks(Piece::Knight,Color::White,num,0) & both(Piece::Knight)
+            | attacks::attacks(Piece::King,Color::White,num,0) & both(Piece::King)
+            | attacks::attacks(Piece::Bishop,Color::White,num,occupied) & diagonal
+            | attacks::attacks(Piece::Rook,Color::White,num,occupied) & straight;
+        bits & occupied
+    }
+
+    // the least valuable piece of the colour among the attackers
+    fn least_valuable(&self,attackers:u64,color:Color)->Option<(usize,Piece)>{
+        PIECES.iter().find_map(|piece|{
+            let bits = attackers & self.bits[color.to_num()][index(*piece)];
+            (bits != 0).then(|| (bits.trailing_zeros() as usize,*piece))
+        })
+    }
+}
+
+// the exchange started by the piece on from moving to to, captured is what stands there (or was taken
+// en passant) and promoted what the piece becomes
+fn exchange(board:&Board,pieces:&Pieces,from:usize,to:usize,captured:Option<(usize,Piece)>,promoted:Option<Piece>)->i32{
+    let Tile::Taken(color,piece,_) = board.data[from] else{
+        return 0;
+    };
+    let mut occupied = attacks::occupancy(board) & !(1 << from);
+    let mut gains = vec![0];
+    if let Some((num,captured)) = captured{
+        gains[0] = value(captured);
+        occupied &= !(1 << num);
+    }
+    if let Some(promoted) = promoted{
+        gains[0] += value(promoted) - value(Piece::Pawn);
+    }
+    // the piece that would be taken next
+    let mut on_square = promoted.unwrap_or(piece);
+    let mut side = color.other();
+
+    loop{
+        let attackers = pieces.attackers(to,occupied);
+        let Some((num,attacker)) = pieces.least_valuable(attackers,side) else{
+            break;
+        };
+        // the king only takes when nothing can take it back
+        if matches!(attacker,Piece::King) && pieces.attackers(to,occupied & !(1 << num)) & pieces.bits[side.other().to_num()].iter().fold(0,|a,b| a | b) != 0{
+            break;
+        }
+        gains.push(value(on_square) - gains[gains.len() - 1]);
+        occupied &= !(1 << num);
+        // promoting again when a pawn takes back on the last rank
+        on_square = attacker;
+        if matches!(attacker,Piece::Pawn) && (to / 8 == 0 || to / 8 == 7){
+            on_square = Piece::Queen;
+            let last = gains.len() - 1;
+            gains[last] += value(Piece::Queen) - value(Piece::Pawn);
+        }
+        side = side.other();
+    }
+
+    // either side may stop taking back when continuing loses
+    while gains.len() > 1{
+        let last = gains.pop().unwrap();
+        let previous = gains.len() - 1;
+        gains[previous] = -(-gains[previous]).max(last);
+    }
+    gains[0]
+}
+
+impl Board{
+
+    // material in centipawns the side to move wins with the move once the exchange on its target square
+    // is played out, negative when it loses the piece, 0 for castling
+    pub fn see(&self,mov:&PossibleMove)->i32{
+        if mov.extra.len() > 1{
+            return 0;
+        }
+        let (from,to) = (mov.start.num,mov.end.num);
+        let captured = match (mov.extra.first(),self.data[to]){
+            (Some((target,_)),_)=>Some((target.num,Piece::Pawn)),
+            (None,Tile::Taken(_,piece,_))=>Some((to,piece)),
+            (None,Tile::Empty)=>None,
+        };
+        let promoted = match self.data[from]{
+            Tile::Taken(_,Piece::Pawn,_) if mov.end.y == 0 || mov.end.y == 7=>Some(promotion(mov.upgrade)),
+            _=>None,
+        };
+        exchange(self,&Pieces::new(self),from,to,captured,promoted)
+    }
+
+    // squares of white's or black's pieces the other side wins material by taking, kings left out
+    pub fn hanging_pieces(&self,white:bool)->Vec<usize>{
+        let color = if white {Color::White} else {Color::Black};
+        let pieces = Pieces::new(self);
+        let occupied = attacks::occupancy(self);
+        let mut res = vec![];
+        for (num,tile) in self.data.iter().enumerate(){
+            let Tile::Taken(c,piece,_) = tile else{
+                continue;
+            };
+            if *c != color || matches!(piece,Piece::King){
+                continue;
+            }
+            let mut attackers = pieces.attackers(num,occupied) & pieces.bits[color.other().to_num()].iter().fold(0,|a,b| a | b);
+            while attackers != 0{
+                let from = attackers.trailing_zeros() as usize;
+                attackers &= attackers - 1;
+                let promoted = match self.data[from]{
+                    Tile::Taken(_,Piece::Pawn,_) if num / 8 == 0 || num / 8 == 7=>Some(Piece::Queen),
+                    _=>None,
+                };
+                if exchange(self,&pieces,from,num,Some((num,*piece)),promoted) > 0{
+                    res.push(num);
+                    break;
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn see(fen:&str,mov:&str)->i32{
+        let board = Board::from_fen(fen).unwrap();
+        board.see(&board.parse_move(mov).unwrap())
+    }
+
+    #[test]
+    fn exchanges(){
+        // the pawn is not defended
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1","Rxe5"),100);
+        // the knight is taken back and the rest does not pay for black
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1","Nxe5"),-220);
+        // the rook behind the rook joins in
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/3R4/3RK3 w - - 0 1","Rxd5"),100);
+        assert_eq!(see("3r2k1/8/8/3p4/8/8/8/3RK3 w - - 0 1","Rxd5"),-400);
+        assert_eq!(see("3r2k1/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1","Rxd5"),-400);
+        assert_eq!(see("4k3/2p5/8/3N4/8/8/8/4K3 w - - 0 1","Nb6"),-320);
+        assert_eq!(see("4k3/8/8/8/8/8/8/4K3 w - - 0 1","Kd2"),0);
+    }
+
+    #[test]
+    fn special_moves(){
+        assert_eq!(see("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1","dxe6"),100);
+        assert_eq!(see("4k3/P7/8/8/8/8/8/4K3 w - - 0 1","a8=Q"),800);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1","a8=Q"),-100);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1","axb8=N"),720);
+        assert_eq!(see("r3k3/8/8/8/8/8/8/4K2R w K - 0 1","O-O"),0);
+        // the king may only take what is not defended
+        assert_eq!(see("4k3/4p3/8/8/8/8/8/4RK2 w - - 0 1","Rxe7+"),-400);
+        assert_eq!(see("4k3/4p3/8/8/8/8/4R3/4RK2 w - - 0 1","Rxe7+"),100);
+    }
+
+    #[test]
+    fn hanging(){
+        let board = Board::from_fen("4k3/8/8/3n4/8/7b/8/3RK1N1 w - - 0 1").unwrap();
+        // the rook takes d5 and the knight h3, neither is defended
+        let mut hanging = board.hanging_pieces(false);
+        hanging.sort();
+        assert_eq!(hanging,[Pos::from_ints(0,2).num,Pos::from_ints(4,4).num]);
+        assert!(board.hanging_pieces(true).is_empty());
+    }
+}
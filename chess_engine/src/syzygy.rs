@@ -0,0 +1,1170 @@
+// Probing of Syzygy endgame tablebases (.rtbw win/draw/loss and .rtbz distance to zeroing files).
+//
+// Squares and pieces are converted to the tablebase convention (a1 = 0, h8 = 63, pieces 1..6 for
+// pawn..king, +8 for black) before encoding. Our board stores files mirrored (x = 0 is the h-file),
+// so a board index maps to a tablebase square with `num ^ 7`.
+//
+// Wdl values are from the point of view of the side to move:
+// -2 loss, -1 loss saved by the 50 move rule, 0 draw, 1 win spoiled by the 50 move rule, 2 win.
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::*;
+use crate::tablegen::{EndgameTables,Outcome};
+
+const TB_PIECES:usize = 7;
+
+const WDL_MAGIC:[u8;4] = [0x71,0xE8,0x23,0x5D];
+const DTZ_MAGIC:[u8;4] = [0xD7,0x66,0x0C,0xA5];
+
+const FLAG_STM:u8 = 1;
+const FLAG_MAPPED:u8 = 2;
+const FLAG_WIN_PLIES:u8 = 4;
+const FLAG_LOSS_PLIES:u8 = 8;
+const FLAG_WIDE:u8 = 16;
+const FLAG_SINGLE_VALUE:u8 = 128;
+
+const PIECE_CHARS:[char;6] = ['K','Q','R','B','N','P'];
+
+fn rank_of(sq:usize)->usize{sq >> 3}
+fn file_of(sq:usize)->usize{sq & 7}
+fn off_diag(sq:usize)->i32{rank_of(sq) as i32 - file_of(sq) as i32}
+fn flip_diag(sq:usize)->usize{((sq >> 3) | (sq << 3)) & 63}
+
+fn sign(x:i32)->i32{x.signum()}
+
+// dtz of the move before a zeroing move (capture or pawn move) that leads to the given wdl
+fn dtz_before_zeroing(wdl:i8)->i32{
+    match wdl{
+        2=>1,
+        1=>101,
+        -1=>-101,
+        -2=>-1,
+        _=>0,
+    }
+}
+
+// lookup tables for the position encoding, computed once per Tablebase
+struct Indices{
+    map_pawns:[usize;64],
+    map_b1h1h7:[usize;64],
+    map_a1d1d4:[usize;64],
+    map_kk:[[usize;64];10],
+    binomial:[[u64;64];TB_PIECES],
+    lead_pawn_idx:[[u64;64];TB_PIECES],
+    lead_pawns_size:[[u64;4];TB_PIECES],
+}
+
+impl Indices{
+    fn new()->Indices{
+        let mut ind = Indices{
+            map_pawns:[0;64],
+            map_b1h1h7:[0;64],
+            map_a1d1d4:[0;64],
+            map_kk:[[0;64];10],
+            binomial:[[0;64];TB_PIECES],
+            lead_pawn_idx:[[0;64];TB_PIECES],
+            lead_pawns_size:[[0;4];TB_PIECES],
+        };
+
+        let mut code = 0;
+        for sq in 0..64{
+            if off_diag(sq) < 0{
+                ind.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        // squares of the a1-d1-d4 triangle, the ones on the diagonal last
+        let mut diagonal = vec![];
+        code = 0;
+        for sq in 0..28{
+            if off_diag(sq) < 0 && file_of(sq) <= 3{
+                ind.map_a1d1d4[sq] = code;
+                code += 1;
+            }else if off_diag(sq) == 0 && file_of(sq) <= 3{
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal{
+            ind.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        // the 462 legal placements of two kings with the first one in the a1-d1-d4 triangle
+        let mut both_on_diagonal = vec![];
+        code = 0;
+        for idx in 0..10{
+            for s1 in 0..28{
+                if ind.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1){
+                    continue;
+                }
+                for s2 in 0..64{
+                    let touching = (file_of(s1) as i32 - file_of(s2) as i32).abs() <= 1
+                        && (rank_of(s1) as i32 - rank_of(s2) as i32).abs() <= 1;
+                    if touching || (off_diag(s1) == 0 && off_diag(s2) > 0){
+                        continue;
+                    }
+                    if off_diag(s1) == 0 && off_diag(s2) == 0{
+                        both_on_diagonal.push((idx,s2));
+                    }else{
+                        ind.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx,s2) in both_on_diagonal{
+            ind.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        ind.binomial[0][0] = 1;
+        for n in 1..64{
+            for k in 0..TB_PIECES.min(n+1){
+                ind.binomial[k][n] = if k > 0 {ind.binomial[k-1][n-1]} else {0}
+                    + if k < n {ind.binomial[k][n-1]} else {0};
+            }
+        }
+
+        // pawn squares a2-h7 mapped so that the leading pawn has the highest value
+        let mut assigned = 0;
+        for lead_count in 1..TB_PIECES{
+            for file in 0..4{
+                let mut idx = 0;
+                for rank in 1..7{
+                    let sq = rank*8 + file;
+                    if lead_count == 1{
+                        ind.map_pawns[sq] = 47 - assigned;
+                        ind.map_pawns[sq ^ 7] = 46 - assigned;
+                        assigned += 2;
+                    }
+                    ind.lead_pawn_idx[lead_count][sq] = idx;
+                    idx += ind.binomial[lead_count-1][ind.map_pawns[sq]];
+                }
+                ind.lead_pawns_size[lead_count][file] = idx;
+            }
+        }
+        ind
+    }
+}
+
+// material signature for one side, e.g. "KRP"
+pub(crate) fn material_of(board:&Board,color:Color)->String{
+    let mut counts = [0;6];
+    for tile in board.data.iter(){
+        if let Tile::Taken(c,piece,_) = tile{
+            if *c == color{
+                counts[match piece{
+                    Piece::King=>0,
+                    Piece::Queen=>1,
+                    Piece::Rook=>2,
+                    Piece::Bishop=>3,
+                    Piece::Knight=>4,
+                    Piece::Pawn=>5,
+                }] += 1;
+            }
+        }
+    }
+    let mut res = String::new();
+    for (i,count) in counts.iter().enumerate(){
+        for _ in 0..*count{
+            res.push(PIECE_CHARS[i]);
+        }
+    }
+    res
+}
+
+// pieces in tablebase encoding, sorted by tablebase square
+fn tb_pieces(board:&Board)->Vec<(usize,u8)>{
+    let mut res = vec![];
+    for sq in 0..64{
+        if let Tile::Taken(color,piece,_) = board.data[sq ^ 7]{
+            let kind = match piece{
+                Piece::Pawn=>1,
+                Piece::Knight=>2,
+                Piece::Bishop=>3,
+                Piece::Rook=>4,
+                Piece::Queen=>5,
+                Piece::King=>6,
+            };
+            res.push((sq,kind + 8*color.to_num() as u8));
+        }
+    }
+    res
+}
+
+pub(crate) fn is_capture(board:&Board,mov:&PossibleMove)->bool{
+    !board.tile_is_empty(mov.end.num) || (is_pawn_move(board,mov) && !mov.extra.is_empty())
+}
+
+fn is_pawn_move(board:&Board,mov:&PossibleMove)->bool{
+    matches!(board.data[mov.start.num],Tile::Taken(_,Piece::Pawn,_))
+}
+
+fn legal_moves(board:&Board)->Vec<PossibleMove>{
+    let mut board = *board;
+    board.get_legal_moves(5)
+}
+
+pub(crate) fn is_in_check(board:&Board,color:Color)->bool{
+    let king = board.king_positions[color.to_num()];
+    !board.check_safety(king.x, king.y, color)
+}
+
+fn is_mate(board:&Board)->bool{
+    is_in_check(board,Color::from_num(board.counter as usize %2)) && legal_moves(board).is_empty()
+}
+
+fn has_castling_rights(board:&Board)->bool{
+    for color in [Color::White,Color::Black]{
+        let y = if color == Color::White {0} else {7};
+        if !matches!(board.data[Pos::from_ints(3,y).num],Tile::Taken(c,Piece::King,PieceInfo::None) if c == color){
+            continue;
+        }
+        for x in [0,7]{
+            if matches!(board.data[Pos::from_ints(x,y).num],Tile::Taken(c,Piece::Rook,PieceInfo::None) if c == color){
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[derive(Clone,Copy)]
+struct TableInfo{
+    piece_count:usize,
+    has_pawns:bool,
+    has_unique_pieces:bool,
+    // pawns of the leading color and of the other color
+    pawn_count:[usize;2],
+    symmetric:bool,
+}
+
+impl TableInfo{
+    fn from_name(name:&str)->Option<TableInfo>{
+        let (white,black) = name.split_once('v')?;
+        let mut counts = [[0usize;6];2];
+        for (side,pieces) in [white,black].iter().enumerate(){
+            for c in pieces.chars(){
+                counts[side][PIECE_CHARS.iter().position(|p| *p == c)?] += 1;
+            }
+            if counts[side][0] != 1{
+                return None;
+            }
+        }
+        let piece_count = white.len() + black.len();
+        if piece_count > TB_PIECES{
+            return None;
+        }
+        let pawns = [counts[0][5],counts[1][5]];
+        let lead_white = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+        Some(TableInfo{
+            piece_count,
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces: counts.iter().any(|side| side[1..].contains(&1)),
+            pawn_count: if lead_white {pawns} else {[pawns[1],pawns[0]]},
+            symmetric: white == black,
+        })
+    }
+}
+
+#[derive(Clone,Default)]
+struct PairsData{
+    flags:u8,
+    min_sym_len:u8,
+    sizeof_block:u64,
+    span:u64,
+    lowest_sym:usize,
+    btree:usize,
+    block_length:usize,
+    block_length_size:u64,
+    sparse_index:usize,
+    sparse_index_size:u64,
+    num_blocks:u64,
+    data:usize,
+    base64:Vec<u64>,
+    symlen:Vec<u8>,
+    pieces:[u8;TB_PIECES],
+    group_idx:[u64;TB_PIECES+1],
+    group_len:[usize;TB_PIECES+1],
+    // offsets of the value maps for win, loss, cursed win and blessed loss (dtz only)
+    map_idx:[usize;4],
+}
+
+struct TableData{
+    bytes:Vec<u8>,
+    // [side to move][leading pawn file, or 0 without pawns]
+    items:Vec<Vec<PairsData>>,
+}
+
+impl TableData{
+
+    fn u8_at(&self,i:usize)->Option<u8>{
+        self.bytes.get(i).copied()
+    }
+
+    fn u16_le(&self,i:usize)->Option<u16>{
+        Some(u16::from_le_bytes(self.bytes.get(i..i+2)?.try_into().ok()?))
+    }
+
+    fn u32_le(&self,i:usize)->Option<u32>{
+        Some(u32::from_le_bytes(self.bytes.get(i..i+4)?.try_into().ok()?))
+    }
+
+    fn u32_be(&self,i:usize)->u32{
+        match self.bytes.get(i..i+4){
+            Some(b)=>u32::from_be_bytes(b.try_into().unwrap()),
+            None=>0,
+        }
+    }
+
+    fn u64_be(&self,i:usize)->u64{
+        ((self.u32_be(i) as u64) << 32) | self.u32_be(i+4) as u64
+    }
+
+    fn parse(bytes:Vec<u8>,info:&TableInfo,dtz:bool,ind:&Indices)->Option<TableData>{
+        let magic = if dtz {DTZ_MAGIC} else {WDL_MAGIC};
+        if bytes.get(0..4)? != magic{
+            return None;
+        }
+        let mut table = TableData{bytes,items:vec![]};
+
+        let flags = table.u8_at(4)?;
+        if (flags & 2 != 0) != info.has_pawns || (flags & 1 != 0) == info.symmetric{
+            return None;
+        }
+        let mut data = 5;
+
+        let sides = if !dtz && !info.symmetric {2} else {1};
+        let files = if info.has_pawns {4} else {1};
+        let pp = info.has_pawns && info.pawn_count[1] > 0;
+        table.items = vec![vec![PairsData::default();files];sides];
+
+        for f in 0..files{
+            let first = table.u8_at(data)?;
+            let second = if pp {table.u8_at(data+1)?} else {0xFF};
+            let order = [[first & 0xF, second & 0xF],[first >> 4, second >> 4]];
+            data += 1 + pp as usize;
+
+            for k in 0..info.piece_count{
+                let byte = table.u8_at(data)?;
+                for (i,side) in table.items.iter_mut().enumerate(){
+                    side[f].pieces[k] = if i == 1 {byte >> 4} else {byte & 0xF};
+                }
+                data += 1;
+            }
+            for (i,side) in table.items.iter_mut().enumerate(){
+                set_groups(info,&mut side[f],order[i],f,ind)?;
+            }
+        }
+        data += data & 1;
+
+        for f in 0..files{
+            for i in 0..sides{
+                data = table.set_sizes(i,f,data)?;
+            }
+        }
+
+        if dtz{
+            for f in 0..files{
+                let flags = table.items[0][f].flags;
+                if flags & FLAG_MAPPED == 0{
+                    continue;
+                }
+                if flags & FLAG_WIDE != 0{
+                    data += data & 1;
+                    for i in 0..4{
+                        table.items[0][f].map_idx[i] = data + 2;
+                        data += 2 * table.u16_le(data)? as usize + 2;
+                    }
+                }else{
+                    for i in 0..4{
+                        table.items[0][f].map_idx[i] = data + 1;
+                        data += table.u8_at(data)? as usize + 1;
+                    }
+                }
+            }
+            data += data & 1;
+        }
+
+        for f in 0..files{
+            for i in 0..sides{
+                let d = &mut table.items[i][f];
+                d.sparse_index = data;
+                data += d.sparse_index_size as usize * 6;
+            }
+        }
+        for f in 0..files{
+            for i in 0..sides{
+                let d = &mut table.items[i][f];
+                d.block_length = data;
+                data += d.block_length_size as usize * 2;
+            }
+        }
+        for f in 0..files{
+            for i in 0..sides{
+                data = (data + 0x3F) & !0x3F;
+                let d = &mut table.items[i][f];
+                d.data = data;
+                data += (d.num_blocks * d.sizeof_block) as usize;
+            }
+        }
+        if data > table.bytes.len(){
+            return None;
+        }
+        Some(table)
+    }
+
+    fn set_sizes(&mut self,side:usize,file:usize,mut data:usize)->Option<usize>{
+        let flags = self.u8_at(data)?;
+        data += 1;
+        if flags & FLAG_SINGLE_VALUE != 0{
+            let d = &mut self.items[side][file];
+            d.flags = flags;
+            // the single stored value
+            d.min_sym_len = self.bytes.get(data).copied()?;
+            return Some(data + 1);
+        }
+
+        let tb_size = {
+            let d = &self.items[side][file];
+            let n = d.group_len.iter().position(|l| *l == 0)?;
+            d.group_idx[n]
+        };
+
+        let sizeof_block = 1u64.checked_shl(self.u8_at(data)? as u32)?;
+        let span = 1u64.checked_shl(self.u8_at(data+1)? as u32)?;
+        let padding = self.u8_at(data+2)? as u64;
+        let num_blocks = self.u32_le(data+3)? as u64;
+        let max_sym_len = self.u8_at(data+7)?;
+        let min_sym_len = self.u8_at(data+8)?;
+        data += 9;
+        if max_sym_len < min_sym_len || min_sym_len == 0{
+            return None;
+        }
+        let lowest_sym = data;
+
+        // canonical huffman code: base64[l] is the lowest symbol of length l + min_sym_len padded to 64 bits
+        let mut base64 = vec![0u64;(max_sym_len - min_sym_len) as usize + 1];
+        for i in (0..base64.len()-1).rev(){
+            let low = self.u16_le(lowest_sym + 2*i)? as u64;
+            let next = self.u16_le(lowest_sym + 2*i + 2)? as u64;
+            base64[i] = (base64[i+1] + low).checked_sub(next)? / 2;
+        }
+        for (i,base) in base64.iter_mut().enumerate(){
+            *base = base.checked_shl(64 - i as u32 - min_sym_len as u32).unwrap_or(0);
+        }
+        data += base64.len() * 2;
+
+        let symlen_size = self.u16_le(data)? as usize;
+        data += 2;
+        let btree = data;
+        if btree + symlen_size*3 > self.bytes.len(){
+            return None;
+        }
+
+        // recursive pairing: every symbol expands to a left and a right symbol
+        let mut symlen = vec![0u8;symlen_size];
+        let mut visited = vec![false;symlen_size];
+        for sym in 0..symlen_size{
+            if !visited[sym]{
+                symlen[sym] = self.set_symlen(btree,sym,&mut symlen,&mut visited)?;
+            }
+        }
+
+        let d = &mut self.items[side][file];
+        d.flags = flags;
+        d.min_sym_len = min_sym_len;
+        d.sizeof_block = sizeof_block;
+        d.span = span;
+        d.sparse_index_size = tb_size.div_ceil(span);
+        d.num_blocks = num_blocks;
+        d.block_length_size = num_blocks + padding;
+        d.lowest_sym = lowest_sym;
+        d.base64 = base64;
+        d.btree = btree;
+        d.symlen = symlen;
+
+        Some(data + symlen_size*3 + (symlen_size & 1))
+    }
+
+    fn set_symlen(&self,btree:usize,sym:usize,symlen:&mut [u8],visited:&mut [bool])->Option<u8>{
+        visited[sym] = true;
+        let (left,right) = self.btree_entry(btree,sym);
+        if right == 0xFFF{
+            return Some(0);
+        }
+        if left >= symlen.len() || right >= symlen.len(){
+            return None;
+        }
+        if !visited[left]{
+            symlen[left] = self.set_symlen(btree,left,symlen,visited)?;
+        }
+        if !visited[right]{
+            symlen[right] = self.set_symlen(btree,right,symlen,visited)?;
+        }
+        Some(symlen[left].wrapping_add(symlen[right]).wrapping_add(1))
+    }
+
+    fn btree_entry(&self,btree:usize,sym:usize)->(usize,usize){
+        let i = btree + 3*sym;
+        let b = [self.bytes[i] as usize,self.bytes[i+1] as usize,self.bytes[i+2] as usize];
+        (((b[1] & 0xF) << 8) | b[0], (b[2] << 4) | (b[1] >> 4))
+    }
+
+    fn decompress_pairs(&self,d:&PairsData,idx:u64)->Option<i32>{
+        if d.flags & FLAG_SINGLE_VALUE != 0{
+            return Some(d.min_sym_len as i32);
+        }
+
+        // the sparse index points to the block holding value number k*span + span/2
+        let k = idx / d.span;
+        let entry = d.sparse_index + 6*k as usize;
+        let mut block = self.u32_le(entry)? as usize;
+        let mut offset = self.u16_le(entry+4)? as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        while offset < 0{
+            block = block.checked_sub(1)?;
+            offset += self.u16_le(d.block_length + 2*block)? as i64 + 1;
+        }
+        loop{
+            let length = self.u16_le(d.block_length + 2*block)? as i64;
+            if offset <= length{
+                break;
+            }
+            offset -= length + 1;
+            block += 1;
+        }
+
+        let mut ptr = d.data + block * d.sizeof_block as usize;
+        let mut buf64 = self.u64_be(ptr);
+        ptr += 8;
+        let mut buf64_size = 64;
+        let min = d.min_sym_len as usize;
+
+        let mut sym;
+        loop{
+            let mut len = 0;
+            while len + 1 < d.base64.len() && buf64 < d.base64[len]{
+                len += 1;
+            }
+            sym = ((buf64 - d.base64[len]) >> (64 - len - min)) as usize;
+            sym += self.u16_le(d.lowest_sym + 2*len)? as usize;
+            let count = *d.symlen.get(sym)? as i64 + 1;
+            if offset < count{
+                break;
+            }
+            offset -= count;
+            len += min;
+            buf64 = buf64.checked_shl(len as u32).unwrap_or(0);
+            buf64_size -= len as i32;
+            if buf64_size <= 32{
+                buf64_size += 32;
+                buf64 |= (self.u32_be(ptr) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        while d.symlen[sym] != 0{
+            let (left,right) = self.btree_entry(d.btree,sym);
+            let left_count = *d.symlen.get(left)? as i64 + 1;
+            if offset < left_count{
+                sym = left;
+            }else{
+                offset -= left_count;
+                sym = right;
+            }
+        }
+        Some(self.btree_entry(d.btree,sym).0 as i32)
+    }
+
+    // dtz values are stored remapped and possibly in full moves instead of plies
+    fn map_dtz(&self,file:usize,value:i32,wdl:i8)->Option<i32>{
+        let d = &self.items[0][file];
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0{
+            let map = d.map_idx[match wdl{2=>0,-2=>1,1=>2,_=>3}];
+            value = if d.flags & FLAG_WIDE != 0{
+                self.u16_le(map + 2*value as usize)? as i32
+            }else{
+                self.u8_at(map + value as usize)? as i32
+            };
+        }
+        if (wdl == 2 && d.flags & FLAG_WIN_PLIES == 0)
+            || (wdl == -2 && d.flags & FLAG_LOSS_PLIES == 0)
+            || wdl == 1 || wdl == -1{
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+}
+
+// splits the pieces of a table into the groups that are encoded together and computes their index factors
+fn set_groups(info:&TableInfo,d:&mut PairsData,order:[u8;2],file:usize,ind:&Indices)->Option<()>{
+    let mut n = 0;
+    let mut first_len:i32 = if info.has_pawns {0} else if info.has_unique_pieces {3} else {2};
+    d.group_len[0] = 1;
+    for i in 1..info.piece_count{
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i-1]{
+            d.group_len[n] += 1;
+        }else{
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    let pp = info.has_pawns && info.pawn_count[1] > 0;
+    let mut next = if pp {2} else {1};
+    let mut free_squares = 64 - d.group_len[0] - if pp {d.group_len[1]} else {0};
+    let mut idx:u64 = 1;
+    let mut k = 0;
+    while next < n || k == order[0] as usize || k == order[1] as usize{
+        if k > 0xF{
+            return None;
+        }
+        if k == order[0] as usize{
+            d.group_idx[0] = idx;
+            idx *= if info.has_pawns{
+                ind.lead_pawns_size[d.group_len[0]][file]
+            }else if info.has_unique_pieces{
+                31332
+            }else{
+                462
+            };
+        }else if k == order[1] as usize{
+            d.group_idx[1] = idx;
+            idx *= ind.binomial[d.group_len[1]][48 - d.group_len[0]];
+        }else{
+            d.group_idx[next] = idx;
+            idx *= ind.binomial[d.group_len[next]][free_squares];
+            free_squares -= d.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+    d.group_idx[n] = idx;
+    Some(())
+}
+
+struct Table{
+    info:TableInfo,
+    path:Option<PathBuf>,
+    data:OnceCell<Option<TableData>>,
+}
+
+impl Table{
+    fn data(&self,dtz:bool,ind:&Indices)->Option<&TableData>{
+        self.data.get_or_init(||{
+            let bytes = std::fs::read(self.path.as_ref()?).ok()?;
+            TableData::parse(bytes,&self.info,dtz,ind)
+        }).as_ref()
+    }
+}
+
+enum Probe{
+    Value(i32),
+    // dtz tables only store one side to move, the other one needs a 1 ply search
+    ChangeStm,
+}
+
+// syzygy tables, with the self generated ones as fallback
+pub struct Tablebase{
+    wdl:HashMap<String,Table>,
+    dtz:HashMap<String,Table>,
+    max_pieces:usize,
+    indices:Box<Indices>,
+    pub(crate) generated:EndgameTables,
+}
+
+impl Default for Tablebase{
+    fn default()->Self{
+        Tablebase::new()
+    }
+}
+
+impl Tablebase{
+
+    pub fn new()->Tablebase{
+        Tablebase{
+            wdl:HashMap::new(),
+            dtz:HashMap::new(),
+            max_pieces:0,
+            indices:Box::new(Indices::new()),
+            generated:EndgameTables::new(),
+        }
+    }
+
+    // registers all tables of a directory, syzygy ones are read on first access
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_directory(&mut self,dir:&Path)->std::io::Result<usize>{
+        let mut added = 0;
+        for entry in std::fs::read_dir(dir)?{
+            let path = entry?.path();
+            let (Some(stem),Some(ext)) = (path.file_stem().and_then(|s| s.to_str()),path.extension().and_then(|s| s.to_str())) else{
+                continue
+            };
+            if ext == "srtb"{
+                if self.generated.add_bytes(&std::fs::read(&path)?).is_some(){
+                    added += 1;
+                }
+                continue;
+            }
+            let Some(info) = TableInfo::from_name(stem) else{continue};
+            let table = Table{info,path:Some(path.clone()),data:OnceCell::new()};
+            match ext{
+                "rtbw"=>self.wdl.insert(stem.to_string(),table),
+                "rtbz"=>self.dtz.insert(stem.to_string(),table),
+                _=>continue,
+            };
+            self.max_pieces = self.max_pieces.max(info.piece_count);
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    // adds a table from the contents of a file named like "KRvK.rtbw"
+    pub fn add_file(&mut self,file_name:&str,bytes:Vec<u8>)->bool{
+        let Some((stem,ext)) = file_name.rsplit_once('.') else{return false};
+        let Some(info) = TableInfo::from_name(stem) else{return false};
+        let dtz = match ext{
+            "rtbw"=>false,
+            "rtbz"=>true,
+            _=>return false,
+        };
+        let Some(data) = TableData::parse(bytes,&info,dtz,&self.indices) else{return false};
+        let table = Table{info,path:None,data:OnceCell::from(Some(data))};
+        if dtz{
+            self.dtz.insert(stem.to_string(),table);
+        }else{
+            self.wdl.insert(stem.to_string(),table);
+        }
+        self.max_pieces = self.max_pieces.max(info.piece_count);
+        true
+    }
+
+    fn can_probe(&self,board:&Board)->bool{
+        let count = board.data.iter().filter(|t| !matches!(t,Tile::Empty)).count();
+        count <= self.max_pieces && board.state == GameState::Ongoing && !has_castling_rights(board)
+    }
+
+    fn probe_table(&self,board:&Board,dtz:bool,wdl:i8)->Option<Probe>{
+        let pieces = tb_pieces(board);
+        if pieces.len() == 2{
+            return Some(Probe::Value(0));
+        }
+
+        let white = material_of(board,Color::White);
+        let black = material_of(board,Color::Black);
+        let tables = if dtz {&self.dtz} else {&self.wdl};
+        let (table,black_stronger) = match tables.get(&format!("{}v{}",white,black)){
+            Some(table)=>(table,false),
+            None=>(tables.get(&format!("{}v{}",black,white))?,true),
+        };
+        let info = &table.info;
+        let data = table.data(dtz,&self.indices)?;
+        let ind = &self.indices;
+
+        // tables are stored with white as the stronger side and for symmetric material only with white to move
+        let black_to_move = board.counter % 2 == 1;
+        let flip = black_stronger || (info.symmetric && black_to_move);
+        let flip_color = if flip {8} else {0};
+        let flip_squares = if flip {56} else {0};
+        let stm = (flip ^ black_to_move) as usize;
+
+        let mut squares = [0usize;TB_PIECES];
+        let mut codes = [0u8;TB_PIECES];
+        let mut size = 0;
+        let mut lead_count = 0;
+        let mut tb_file = 0;
+        let mut lead_pawn = 0;
+
+        if info.has_pawns{
+            lead_pawn = data.items[0][0].pieces[0] ^ flip_color;
+            for (sq,code) in pieces.iter(){
+                if *code == lead_pawn{
+                    squares[size] = sq ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_count = size;
+            let mut lead = 0;
+            for i in 1..lead_count{
+                if ind.map_pawns[squares[i]] > ind.map_pawns[squares[lead]]{
+                    lead = i;
+                }
+            }
+            squares.swap(0,lead);
+            tb_file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        if dtz{
+            let flags = data.items[0][tb_file].flags;
+            let both_sides_stored = info.symmetric && !info.has_pawns;
+            if (flags & FLAG_STM) as usize != stm && !both_sides_stored{
+                return Some(Probe::ChangeStm);
+            }
+        }
+
+        for (sq,code) in pieces.iter(){
+            if info.has_pawns && *code == lead_pawn{
+                continue;
+            }
+            squares[size] = sq ^ flip_squares;
+            codes[size] = code ^ flip_color;
+            size += 1;
+        }
+
+        let side = if dtz {0} else {stm.min(data.items.len()-1)};
+        let d = &data.items[side][tb_file];
+
+        // order the pieces like the table does
+        for i in lead_count..size.saturating_sub(1){
+            for j in i+1..size{
+                if d.pieces[i] == codes[j]{
+                    codes.swap(i,j);
+                    squares.swap(i,j);
+                    break;
+                }
+            }
+        }
+
+        if file_of(squares[0]) > 3{
+            for sq in squares[..size].iter_mut(){
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx:u64;
+        if info.has_pawns{
+            idx = ind.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_by_key(|sq| ind.map_pawns[*sq]);
+            for (i,sq) in squares.iter().enumerate().take(lead_count).skip(1){
+                idx += ind.binomial[i][ind.map_pawns[*sq]];
+            }
+        }else{
+            if rank_of(squares[0]) > 3{
+                for sq in squares[..size].iter_mut(){
+                    *sq ^= 56;
+                }
+            }
+            // the first piece of the leading group off the diagonal goes below it
+            for i in 0..d.group_len[0]{
+                if off_diag(squares[i]) == 0{
+                    continue;
+                }
+                if off_diag(squares[i]) > 0{
+                    for sq in squares[i..size].iter_mut(){
+                        *sq = flip_diag(*sq);
+                    }
+                }
+                break;
+            }
+
+            if info.has_unique_pieces{
+                let s = squares;
+                let adjust1 = (s[1] > s[0]) as usize;
+                let adjust2 = (s[2] > s[0]) as usize + (s[2] > s[1]) as usize;
+                idx = if off_diag(s[0]) != 0{
+                    ((ind.map_a1d1d4[s[0]] * 63 + (s[1] - adjust1)) * 62 + s[2] - adjust2) as u64
+                }else if off_diag(s[1]) != 0{
+                    ((6*63 + rank_of(s[0]) * 28 + ind.map_b1h1h7[s[1]]) * 62 + s[2] - adjust2) as u64
+                }else if off_diag(s[2]) != 0{
+                    (6*63*62 + 4*28*62
+                        + rank_of(s[0]) * 7*28
+                        + (rank_of(s[1]) - adjust1) * 28
+                        + ind.map_b1h1h7[s[2]]) as u64
+                }else{
+                    (6*63*62 + 4*28*62 + 4*7*28
+                        + rank_of(s[0]) * 7*6
+                        + (rank_of(s[1]) - adjust1) * 6
+                        + (rank_of(s[2]) - adjust2)) as u64
+                };
+            }else{
+                idx = ind.map_kk[ind.map_a1d1d4[squares[0]]][squares[1]] as u64;
+            }
+        }
+
+        // remaining groups, each as a combination of the squares not taken by earlier groups
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = info.has_pawns && info.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0{
+            let len = d.group_len[next];
+            squares[start..start+len].sort();
+            let mut n:u64 = 0;
+            for i in 0..len{
+                let sq = squares[start+i];
+                let adjust = squares[..start].iter().filter(|s| sq > **s).count();
+                let free = (sq - adjust).checked_sub(if remaining_pawns {8} else {0})?;
+                n += ind.binomial[i+1][free];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        let value = data.decompress_pairs(d,idx)?;
+        Some(Probe::Value(if dtz {data.map_dtz(tb_file,value,wdl)?} else {value - 2}))
+    }
+
+    // wdl of the position, taking into account captures the tables store as "don't care"
+    // the flag reports whether the best move is a zeroing one, in which case the dtz tables can't be trusted
+    fn search(&self,board:&Board,check_zeroing:bool)->Option<(i8,bool)>{
+        let moves = legal_moves(board);
+        let mut best:i8 = -2;
+        let mut count = 0;
+
+        for mov in moves.iter(){
+            if !is_capture(board,mov) && (!check_zeroing || !is_pawn_move(board,mov)){
+                continue;
+            }
+            count += 1;
+            let mut next = *board;
+            next.make_possible_move(mov);
+            let value = -self.search(&next,false)?.0;
+            if value > best{
+                best = value;
+                if value >= 2{
+                    return Some((value,true));
+                }
+            }
+        }
+
+        let no_more_moves = count > 0 && count == moves.len();
+        let value = if no_more_moves{
+            best
+        }else{
+            match self.probe_table(board,false,0)?{
+                Probe::Value(v)=>v as i8,
+                Probe::ChangeStm=>return None,
+            }
+        };
+
+        if best >= value{
+            return Some((best,best > 0 || no_more_moves));
+        }
+        Some((value,false))
+    }
+
+    pub fn probe_wdl(&self,board:&Board)->Option<i8>{
+        if self.can_probe(board){
+            if let Some((wdl,_)) = self.search(board,false){
+                return Some(wdl);
+            }
+        }
+        match self.generated.probe(board)?{
+            Outcome::Win(_)=>Some(2),
+            Outcome::Draw=>Some(0),
+            Outcome::Loss(_)=>Some(-2),
+        }
+    }
+
+    // distance to the next capture or pawn move in plies, signed like the wdl
+    pub fn probe_dtz(&self,board:&Board)->Option<i32>{
+        if !self.can_probe(board){
+            return None;
+        }
+        let (wdl,zeroing) = self.search(board,true)?;
+        if wdl == 0{
+            return Some(0);
+        }
+        if zeroing{
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        match self.probe_table(board,true,wdl)?{
+            Probe::Value(dtz)=>{
+                let cursed = if wdl == 1 || wdl == -1 {100} else {0};
+                Some((dtz + cursed) * sign(wdl as i32))
+            }
+            Probe::ChangeStm=>{
+                let mut min_dtz = 0xFFFF;
+                for mov in legal_moves(board).iter(){
+                    let zeroing = is_capture(board,mov) || is_pawn_move(board,mov);
+                    let mut next = *board;
+                    next.make_possible_move(mov);
+                    let mut dtz = if zeroing{
+                        -dtz_before_zeroing(self.search(&next,false)?.0)
+                    }else{
+                        -self.probe_dtz(&next)?
+                    };
+                    if dtz == 1 && is_mate(&next){
+                        min_dtz = 1;
+                    }
+                    if !zeroing{
+                        dtz += sign(dtz);
+                    }
+                    if dtz < min_dtz && sign(dtz) == sign(wdl as i32){
+                        min_dtz = dtz;
+                    }
+                }
+                Some(if min_dtz == 0xFFFF {-1} else {min_dtz})
+            }
+        }
+    }
+
+    // the tablebase optimal move with the distance to zeroing (syzygy) or to mate (generated tables) counting the move, positive when winning
+    pub fn best_move(&self,board:&Board)->Option<(PossibleMove,i32)>{
+        if let Some(res) = self.syzygy_best_move(board){
+            return Some(res);
+        }
+        let (mov,outcome) = self.generated.best_move(board)?;
+        Some((mov,match outcome{
+            Outcome::Win(plies)=>-(plies as i32 + 1),
+            Outcome::Draw=>0,
+            Outcome::Loss(plies)=>plies as i32 + 1,
+        }))
+    }
+
+    // mate if possible, else the fastest win, a draw, or the slowest loss
+    fn syzygy_best_move(&self,board:&Board)->Option<(PossibleMove,i32)>{
+        if !self.can_probe(board){
+            return None;
+        }
+        let mut best:Option<(i32,PossibleMove,i32)> = None;
+        for mov in legal_moves(board){
+            let mut next = *board;
+            next.make_possible_move(&mov);
+            if is_mate(&next){
+                return Some((mov,1));
+            }
+            let dtz = if is_capture(board,&mov) || is_pawn_move(board,&mov){
+                dtz_before_zeroing(-self.search(&next,false)?.0)
+            }else{
+                let dtz = -self.probe_dtz(&next)?;
+                dtz + sign(dtz)
+            };
+            let rank = match dtz{
+                d if d > 0=>(1<<20) - d,
+                d if d < 0=>-(1<<20) - d,
+                _=>0,
+            };
+            if best.as_ref().is_none_or(|b| rank > b.0){
+                best = Some((rank,mov,dtz));
+            }
+        }
+        best.map(|(_,mov,dtz)| (mov,dtz))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests{
+    use super::*;
+
+    // a KRvK.rtbw whose every position has the same value per side to move, as the generator writes
+    // tables without information. values are wdl + 2
+    pub(crate) fn single_value_wdl(white_to_move:u8,black_to_move:u8)->Vec<u8>{
+        let mut bytes = WDL_MAGIC.to_vec();
+        // not symmetric, no pawns, the groups in their natural order
+        bytes.extend([1,0]);
+        // white king, white rook and black king, the same for both sides to move
+        bytes.extend([0x66,0x44,0xEE]);
+        bytes.push(0);
+        bytes.extend([FLAG_SINGLE_VALUE,white_to_move,FLAG_SINGLE_VALUE,black_to_move]);
+        bytes.resize(64,0);
+        bytes
+    }
+
+    #[test]
+    fn indices(){
+        let ind = Indices::new();
+        assert_eq!(ind.binomial[2][5],10);
+        assert_eq!(ind.binomial[3][62],37820);
+        assert_eq!((0..64).filter(|sq| off_diag(*sq) < 0).map(|sq| ind.map_b1h1h7[sq]).max(),Some(27));
+
+        // a1-d1-d4 with the diagonal squares a1, b2, c3 and d4 last
+        let triangle:Vec<usize> = (0..28).filter(|sq| off_diag(*sq) <= 0 && file_of(*sq) <= 3).collect();
+        let mut codes:Vec<usize> = triangle.iter().map(|sq| ind.map_a1d1d4[*sq]).collect();
+        codes.sort();
+        assert_eq!(codes,(0..10).collect::<Vec<_>>());
+        assert_eq!([0,9,18,27].map(|sq| ind.map_a1d1d4[sq]),[6,7,8,9]);
+
+        // every legal pair of kings gets its own code below 462
+        let mut seen = vec![false;462];
+        for s1 in triangle.iter().copied(){
+            for s2 in 0..64{
+                let touching = file_of(s1).abs_diff(file_of(s2)) <= 1 && rank_of(s1).abs_diff(rank_of(s2)) <= 1;
+                if touching || (off_diag(s1) == 0 && off_diag(s2) > 0){
+                    continue;
+                }
+                let code = ind.map_kk[ind.map_a1d1d4[s1]][s2];
+                assert!(!seen[code],"{} {}",s1,s2);
+                seen[code] = true;
+            }
+        }
+        assert!(seen.iter().all(|s| *s));
+
+        // the pawn squares a2-h7 in 48 codes, each file pair of a single leading pawn has 6 squares
+        let mut pawns:Vec<usize> = (8..56).map(|sq| ind.map_pawns[sq]).collect();
+        pawns.sort();
+        assert_eq!(pawns,(0..48).collect::<Vec<_>>());
+        assert_eq!(ind.lead_pawns_size[1],[6,6,6,6]);
+    }
+
+    #[test]
+    fn table_info(){
+        let info = TableInfo::from_name("KRPvKR").unwrap();
+        assert_eq!((info.piece_count,info.has_pawns,info.has_unique_pieces,info.pawn_count,info.symmetric),(5,true,true,[1,0],false));
+        let info = TableInfo::from_name("KRvKPP").unwrap();
+        assert_eq!(info.pawn_count,[2,0]);
+        let info = TableInfo::from_name("KPvKPP").unwrap();
+        assert_eq!(info.pawn_count,[1,2]);
+        let info = TableInfo::from_name("KNNvKNN").unwrap();
+        assert!(info.symmetric && !info.has_unique_pieces);
+        for name in ["KRK","KKvK","KRvR","KXvK","KQRBNvKQP"]{
+            assert!(TableInfo::from_name(name).is_none(),"{}",name);
+        }
+    }
+
+    #[test]
+    fn encoding(){
+        let board = Board::new();
+        assert_eq!(material_of(&board,Color::White),"KQRRBBNNPPPPPPPP");
+        // a1 is square 0, white pieces count from 1 and black ones from 9
+        let pieces = tb_pieces(&board);
+        assert_eq!(&pieces[..5],&[(0,4),(1,2),(2,3),(3,5),(4,6)]);
+        assert_eq!(pieces[31],(63,12));
+
+        let d = |name:&str,pieces:&[u8]|{
+            let info = TableInfo::from_name(name).unwrap();
+            let mut d = PairsData::default();
+            d.pieces[..pieces.len()].copy_from_slice(pieces);
+            set_groups(&info,&mut d,[0,0xF],0,&Indices::new()).unwrap();
+            d
+        };
+        // three unique pieces together, pairs of kings with the rest as combinations of the free squares
+        assert_eq!(d("KRvK",&[6,4,14]).group_idx[..2],[1,31332]);
+        assert_eq!(d("KRRvK",&[6,14,4,4]).group_idx[..3],[1,462,462*1891]);
+    }
+
+    #[test]
+    fn single_value_tables(){
+        let mut tablebase = Tablebase::new();
+        assert!(!tablebase.add_file("KRvK.rtbz",single_value_wdl(4,0)));
+        assert!(!tablebase.add_file("KRvK.rtbw",single_value_wdl(4,0)[..40].to_vec()));
+        assert!(!tablebase.add_file("KQvK.rtbw",vec![0;64]));
+        assert!(tablebase.add_file("KRvK.rtbw",single_value_wdl(4,0)));
+
+        let wdl = |fen:&str| tablebase.probe_wdl(&Board::from_fen(fen).unwrap());
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/R3K3 w - - 0 1"),Some(2));
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/R3K3 b - - 0 1"),Some(-2));
+        // the same with the colors swapped
+        assert_eq!(wdl("r3k3/8/8/8/8/8/8/4K3 b - - 0 1"),Some(2));
+        // captures are searched instead of read from the table
+        assert_eq!(wdl("8/8/8/8/8/8/8/kR4K1 b - - 0 1"),Some(0));
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/Q3K3 w - - 0 1"),None);
+        // without the dtz table there is no distance
+        assert_eq!(tablebase.probe_dtz(&Board::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap()),None);
+    }
+}
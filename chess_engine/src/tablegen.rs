@@ -0,0 +1,697 @@
+// Endgame tables for up to four pieces, generated by retrograde analysis with the board's own move generator.
+//
+// Positions are indexed by side to move, the white king reduced by symmetry, the black king and the
+// remaining pieces (white ones first, in the order of the material signature). Pawnless tables use all
+// 8 board symmetries, tables with pawns only the left/right mirror. En passant rights are ignored.
+//
+// Every position is stored as one byte: 0 draw (or not a legal position), 1..=0x7F a win with mate in
+// 2*v-1 plies and 0x80|v a loss with mate in 2*v plies. Files are the header followed by the bytes
+// run length encoded.
+
+use std::collections::HashMap;
+
+use crate::*;
+use crate::syzygy::{material_of,is_capture,is_in_check};
+
+pub const MAX_PIECES:usize = 4;
+
+const MAGIC:[u8;4] = *b"SRTB";
+const VERSION:u8 = 1;
+
+// canonical white king squares for pawnless tables: a triangle of the board's lower left quarter
+const KING_TRIANGLE:[usize;10] = [0,1,2,3,9,10,11,18,19,27];
+
+#[derive(Clone,Copy,PartialEq,Debug)]
+pub enum Outcome{
+    // plies until mate
+    Win(u32),
+    Draw,
+    Loss(u32),
+}
+
+impl Outcome{
+    fn from_byte(byte:u8)->Outcome{
+        if byte == 0{
+            Outcome::Draw
+        }else if byte & 0x80 != 0{
+            Outcome::Loss(2 * (byte & 0x7F) as u32)
+        }else{
+            Outcome::Win(2 * byte as u32 - 1)
+        }
+    }
+
+    fn to_byte(self)->u8{
+        match self{
+            Outcome::Win(plies)=>plies.div_ceil(2).min(0x7F) as u8,
+            Outcome::Loss(plies)=>0x80 | (plies / 2).min(0x7F) as u8,
+            Outcome::Draw=>0,
+        }
+    }
+}
+
+fn piece_char(piece:Piece)->char{
+    match piece{
+        Piece::King=>'K',
+        Piece::Queen=>'Q',
+        Piece::Rook=>'R',
+        Piece::Bishop=>'B',
+        Piece::Knight=>'N',
+        Piece::Pawn=>'P',
+    }
+}
+
+fn char_piece(c:char)->Option<Piece>{
+    Some(match c{
+        'K'=>Piece::King,
+        'Q'=>Piece::Queen,
+        'R'=>Piece::Rook,
+        'B'=>Piece::Bishop,
+        'N'=>Piece::Knight,
+        'P'=>Piece::Pawn,
+        _=>return None,
+    })
+}
+
+fn side_value(side:&str)->i32{
+    side.chars().filter_map(char_piece).map(|p| Tile::Taken(Color::White,p,PieceInfo::None).get_value() as i32).sum()
+}
+
+fn sort_side(side:&str)->String{
+    let order = "KQRBNP";
+    let mut chars:Vec<char> = side.chars().collect();
+    chars.sort_by_key(|c| order.find(*c));
+    chars.into_iter().collect()
+}
+
+// tables are stored with the stronger side as white
+fn normalize(white:&str,black:&str)->String{
+    let (white,black) = (sort_side(white),sort_side(black));
+    if (side_value(&white),&white) >= (side_value(&black),&black){
+        format!("{}v{}",white,black)
+    }else{
+        format!("{}v{}",black,white)
+    }
+}
+
+fn transform(sq:usize,symmetry:u8)->usize{
+    let (mut x,mut y) = (sq % 8,sq / 8);
+    if symmetry & 1 != 0{
+        x = 7 - x;
+    }
+    if symmetry & 2 != 0{
+        y = 7 - y;
+    }
+    if symmetry & 4 != 0{
+        std::mem::swap(&mut x,&mut y);
+    }
+    y*8 + x
+}
+
+fn make_board(pieces:&[(usize,Color,Piece)],black_to_move:bool)->Board{
+    let mut board = Board::from_nums([0;64]);
+    for &(sq,color,piece) in pieces{
+        let start_rank = if color == Color::White {1} else {6};
+        let info = match piece{
+            Piece::Pawn if sq / 8 == start_rank=>PieceInfo::None,
+            _=>PieceInfo::Moved,
+        };
+        let tile = Tile::Taken(color,piece,info);
+        board.data[sq] = tile;
+        if matches!(piece,Piece::King){
+            board.king_positions[color.to_num()] = Pos::from_num(sq);
+        }
+        board.value_counts[color.to_num()] += tile.get_value() as i32;
+    }
+    board.counter = black_to_move as i32;
+    board
+}
+
+// the same position seen from the other side: colors swapped and the board mirrored vertically
+fn flip_colors(board:&Board)->Board{
+    let mut pieces = vec![];
+    for (i,tile) in board.data.iter().enumerate(){
+        if let Tile::Taken(color,piece,_) = tile{
+            pieces.push((i ^ 56,color.other(),*piece));
+        }
+    }
+    make_board(&pieces,board.counter % 2 == 0)
+}
+
+// legal moves including under promotions
+pub(crate) fn all_moves(board:&Board)->Vec<PossibleMove>{
+    let mut board = *board;
+    let mut res = vec![];
+    for mov in board.get_legal_moves(5){
+        let promotes = matches!(board.data[mov.start.num],Tile::Taken(_,Piece::Pawn,_)) && (mov.end.y == 0 || mov.end.y == 7);
+        if promotes{
+            for upgrade in [5,1,2,3]{
+                res.push(PossibleMove{upgrade,..mov.clone()});
+            }
+        }else{
+            res.push(mov);
+        }
+    }
+    res
+}
+
+pub(crate) fn is_promotion(board:&Board,mov:&PossibleMove)->bool{
+    matches!(board.data[mov.start.num],Tile::Taken(_,Piece::Pawn,_)) && (mov.end.y == 0 || mov.end.y == 7)
+}
+
+struct Layout{
+    white:String,
+    black:String,
+    // all pieces but the kings
+    pieces:Vec<(Color,Piece)>,
+    pawns:bool,
+    size:usize,
+}
+
+impl Layout{
+    fn new(name:&str)->Option<Layout>{
+        let (white,black) = name.split_once('v')?;
+        let mut pieces = vec![];
+        for (side,color) in [(white,Color::White),(black,Color::Black)]{
+            if side.chars().filter(|c| *c == 'K').count() != 1 || !side.starts_with('K'){
+                return None;
+            }
+            for c in side.chars().skip(1){
+                pieces.push((color,char_piece(c)?));
+            }
+        }
+        if pieces.len() + 2 > MAX_PIECES || name != normalize(white,black){
+            return None;
+        }
+        let pawns = pieces.iter().any(|(_,p)| matches!(p,Piece::Pawn));
+        let king_squares = if pawns {32} else {10};
+        Some(Layout{
+            white:white.to_string(),
+            black:black.to_string(),
+            size:2 * king_squares * 64usize.pow(pieces.len() as u32 + 1),
+            pieces,
+            pawns,
+        })
+    }
+
+    fn king_squares(&self)->usize{
+        if self.pawns {32} else {10}
+    }
+
+    // symmetries that bring the white king into the canonical region, two if it ends on the diagonal
+    fn symmetries(&self,king:usize)->Vec<u8>{
+        let mut symmetry = 0;
+        if king % 8 > 3{
+            symmetry |= 1;
+        }
+        if self.pawns{
+            return vec![symmetry];
+        }
+        if king / 8 > 3{
+            symmetry |= 2;
+        }
+        let king = transform(king,symmetry);
+        if king / 8 > king % 8{
+            vec![symmetry | 4]
+        }else if king / 8 == king % 8{
+            vec![symmetry,symmetry | 4]
+        }else{
+            vec![symmetry]
+        }
+    }
+
+    // the board must have this table's material with the same colors
+    fn index(&self,board:&Board)->usize{
+        let mut squares = [0;MAX_PIECES];
+        squares[0] = board.king_positions[0].num;
+        squares[1] = board.king_positions[1].num;
+        let mut used = [false;64];
+        for (i,(color,piece)) in self.pieces.iter().enumerate(){
+            let sq = (0..64).find(|sq| !used[*sq] && matches!(board.data[*sq],
+                Tile::Taken(c,p,_) if c == *color && piece_char(p) == piece_char(*piece))).unwrap();
+            used[sq] = true;
+            squares[i+2] = sq;
+        }
+
+        // equivalent positions must get the same index, so take the smallest one
+        self.symmetries(squares[0]).into_iter().map(|symmetry|{
+            let king = transform(squares[0],symmetry);
+            let king = if self.pawns{
+                (king / 8)*4 + king % 8
+            }else{
+                KING_TRIANGLE.iter().position(|sq| *sq == king).unwrap()
+            };
+            let mut transformed = [0;MAX_PIECES];
+            for (i,sq) in squares[..self.pieces.len()+2].iter().enumerate(){
+                transformed[i] = transform(*sq,symmetry);
+            }
+            // a symmetry can reverse identical pieces, they are indexed in square order after it
+            let mut start = 2;
+            while start < self.pieces.len() + 2{
+                let same = self.pieces[start-2..].iter().take_while(|(c,p)| *c == self.pieces[start-2].0
+                    && piece_char(*p) == piece_char(self.pieces[start-2].1)).count();
+                transformed[start..start+same].sort_unstable();
+                start += same;
+            }
+            let mut idx = (board.counter as usize % 2) * self.king_squares() + king;
+            for sq in transformed[1..self.pieces.len()+2].iter(){
+                idx = idx*64 + sq;
+            }
+            idx
+        }).min().unwrap()
+    }
+
+    // the position of an index, None if it is not a legal one
+    fn board(&self,idx:usize)->Option<Board>{
+        let count = self.pieces.len() + 2;
+        let mut squares = [0;MAX_PIECES];
+        let mut rest = idx;
+        for i in (1..count).rev(){
+            squares[i] = rest % 64;
+            rest /= 64;
+        }
+        let king = rest % self.king_squares();
+        let black_to_move = rest / self.king_squares() == 1;
+        squares[0] = if self.pawns {(king / 4)*8 + king % 4} else {KING_TRIANGLE[king]};
+
+        // most slots are impossible, drop them before a board is built
+        for i in 0..count{
+            if squares[..i].contains(&squares[i]){
+                return None;
+            }
+        }
+        let (white_king,black_king) = (Pos::from_num(squares[0]),Pos::from_num(squares[1]));
+        if white_king.x.abs_diff(black_king.x) <= 1 && white_king.y.abs_diff(black_king.y) <= 1{
+            return None;
+        }
+        let mut pieces = vec![(squares[0],Color::White,Piece::King),(squares[1],Color::Black,Piece::King)];
+        for (i,(color,piece)) in self.pieces.iter().enumerate(){
+            let rank = squares[i+2] / 8;
+            if matches!(piece,Piece::Pawn) && (rank == 0 || rank == 7){
+                return None;
+            }
+            pieces.push((squares[i+2],*color,*piece));
+        }
+
+        let board = make_board(&pieces,black_to_move);
+        let waiting = Color::from_num(1 - board.counter as usize % 2);
+        if is_in_check(&board,waiting){
+            return None;
+        }
+        Some(board)
+    }
+
+    // indices of the positions the side that just moved could have come from without a capture or promotion
+    fn predecessors(&self,board:&Board)->Vec<usize>{
+        let mover = Color::from_num(1 - board.counter as usize % 2);
+        let mut res = vec![];
+        for sq in 0..64{
+            let Tile::Taken(color,piece,_) = board.data[sq] else{continue};
+            if color != mover{
+                continue;
+            }
+            let pos = Pos::from_num(sq);
+            let mut origins = vec![];
+            if matches!(piece,Piece::Pawn){
+                let dir = mover.get_dir();
+                if let Ok(one) = pos.step(0,-dir){
+                    if board.tile_is_empty(one.num) && one.y != 0 && one.y != 7{
+                        origins.push(one.num);
+                        let double_rank = if mover == Color::White {3} else {4};
+                        if pos.y == double_rank{
+                            let two = one.step(0,-dir).unwrap();
+                            if board.tile_is_empty(two.num){
+                                origins.push(two.num);
+                            }
+                        }
+                    }
+                }
+            }else{
+                for mov in board.get_possible_moves_for_pos(pos,mover,0){
+                    if mov.extra.is_empty() && board.tile_is_empty(mov.end.num){
+                        origins.push(mov.end.num);
+                    }
+                }
+            }
+
+            for origin in origins{
+                let mut pieces = vec![(origin,color,piece)];
+                for (i,tile) in board.data.iter().enumerate(){
+                    if let Tile::Taken(c,p,_) = tile{
+                        if i != sq{
+                            pieces.push((i,*c,*p));
+                        }
+                    }
+                }
+                let pred = make_board(&pieces,mover == Color::Black);
+                if !is_in_check(&pred,mover.other()){
+                    res.push(self.index(&pred));
+                }
+            }
+        }
+        res.sort_unstable();
+        res.dedup();
+        res
+    }
+}
+
+struct Table{
+    layout:Layout,
+    values:Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct EndgameTables{
+    tables:HashMap<String,Table>,
+}
+
+impl EndgameTables{
+
+    pub fn new()->EndgameTables{
+        EndgameTables{tables:HashMap::new()}
+    }
+
+    pub fn names(&self)->Vec<String>{
+        let mut names:Vec<String> = self.tables.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // generates the table for a material signature like "KRvKP", including all tables it converts into
+    pub fn generate(&mut self,name:&str)->bool{
+        let Some((white,black)) = name.split_once('v') else{return false};
+        let name = normalize(white,black);
+        if self.tables.contains_key(&name){
+            return true;
+        }
+        let Some(layout) = Layout::new(&name) else{return false};
+
+        // captures and promotions lead into smaller or different tables
+        for (side,other,white_side) in [(&layout.white,&layout.black,true),(&layout.black,&layout.white,false)]{
+            for (i,c) in side.char_indices().skip(1){
+                let mut rest = side.clone();
+                rest.remove(i);
+                let mut subs = vec![rest.clone()];
+                if c == 'P'{
+                    for promoted in ['Q','R','B','N']{
+                        let mut sub = rest.clone();
+                        sub.push(promoted);
+                        subs.push(sub);
+                    }
+                }
+                for sub in subs{
+                    let (w,b) = if white_side {(&sub,other)} else {(other,&sub)};
+                    if (w.len() > 1 || b.len() > 1) && !self.generate(&format!("{}v{}",w,b)){
+                        return false;
+                    }
+                }
+            }
+        }
+
+        let values = self.retrograde(&layout);
+        self.tables.insert(name,Table{layout,values});
+        true
+    }
+
+    fn retrograde(&self,layout:&Layout)->Vec<u8>{
+        let mut values = vec![0u8;layout.size];
+        let mut remaining = vec![0u8;layout.size];
+        let mut worst_loss = vec![0u32;layout.size];
+        let mut has_draw = vec![false;layout.size];
+        let mut has_win = vec![false;layout.size];
+        let mut buckets:Vec<Vec<usize>> = vec![];
+
+        fn push(buckets:&mut Vec<Vec<usize>>,plies:u32,idx:usize){
+            let plies = plies as usize;
+            if buckets.len() <= plies{
+                buckets.resize(plies + 1,vec![]);
+            }
+            buckets[plies].push(idx);
+        }
+
+        for idx in 0..layout.size{
+            let Some(board) = layout.board(idx) else{continue};
+            // mirrored positions and swapped equal pieces are solved once, under the index probes use
+            if layout.index(&board) != idx{
+                continue;
+            }
+            let moves = all_moves(&board);
+            if moves.is_empty(){
+                if is_in_check(&board,Color::from_num(board.counter as usize % 2)){
+                    values[idx] = Outcome::Loss(0).to_byte();
+                    push(&mut buckets,0,idx);
+                }
+                continue;
+            }
+
+            let mut children = vec![];
+            let mut best_win:Option<u32> = None;
+            for mov in moves.iter(){
+                let mut child = board;
+                child.make_possible_move(mov);
+                if !is_capture(&board,mov) && !is_promotion(&board,mov){
+                    children.push(layout.index(&child));
+                    continue;
+                }
+                match self.probe(&child){
+                    Some(Outcome::Loss(plies))=>best_win = Some(best_win.map_or(plies + 1,|w| w.min(plies + 1))),
+                    Some(Outcome::Win(plies))=>worst_loss[idx] = worst_loss[idx].max(plies + 1),
+                    _=>has_draw[idx] = true,
+                }
+            }
+            children.sort_unstable();
+            children.dedup();
+            remaining[idx] = children.len() as u8;
+
+            if let Some(plies) = best_win{
+                has_win[idx] = true;
+                push(&mut buckets,plies,idx);
+            }else if children.is_empty() && !has_draw[idx]{
+                values[idx] = Outcome::Loss(worst_loss[idx]).to_byte();
+                push(&mut buckets,worst_loss[idx],idx);
+            }
+        }
+
+        // wins have an odd number of plies to mate, losses an even one
+        let mut plies = 0;
+        while plies < buckets.len(){
+            let entries = std::mem::take(&mut buckets[plies]);
+            let level = plies as u32;
+            for idx in entries{
+                if level % 2 == 1{
+                    if values[idx] != 0{
+                        continue;
+                    }
+                    values[idx] = Outcome::Win(level).to_byte();
+                }else if values[idx] != Outcome::Loss(level).to_byte(){
+                    continue;
+                }
+
+                let board = layout.board(idx).unwrap();
+                let lost = values[idx] & 0x80 != 0;
+                for pred in layout.predecessors(&board){
+                    if values[pred] != 0{
+                        continue;
+                    }
+                    if lost{
+                        push(&mut buckets,level + 1,pred);
+                    }else if remaining[pred] > 0{
+                        remaining[pred] -= 1;
+                        worst_loss[pred] = worst_loss[pred].max(level + 1);
+                        if remaining[pred] == 0 && !has_draw[pred] && !has_win[pred]{
+                            values[pred] = Outcome::Loss(worst_loss[pred]).to_byte();
+                            push(&mut buckets,worst_loss[pred],pred);
+                        }
+                    }
+                }
+            }
+            plies += 1;
+        }
+        values
+    }
+
+    // outcome for the side to move, None if there is no table for the material
+    pub fn probe(&self,board:&Board)->Option<Outcome>{
+        let count = board.data.iter().filter(|t| !matches!(t,Tile::Empty)).count();
+        if count > MAX_PIECES{
+            return None;
+        }
+        let white = material_of(board,Color::White);
+        let black = material_of(board,Color::Black);
+        if white == "K" && black == "K"{
+            return Some(Outcome::Draw);
+        }
+        if let Some(table) = self.tables.get(&format!("{}v{}",white,black)){
+            return Some(Outcome::from_byte(table.values[table.layout.index(board)]));
+        }
+        let table = self.tables.get(&format!("{}v{}",black,white))?;
+        Some(Outcome::from_byte(table.values[table.layout.index(&flip_colors(board))]))
+    }
+
+    // the quickest mate, a draw or the longest resistance, with the outcome after the move
+    pub fn best_move(&self,board:&Board)->Option<(PossibleMove,Outcome)>{
+        self.probe(board)?;
+        let mut best:Option<(i64,PossibleMove,Outcome)> = None;
+        for mov in all_moves(board){
+            let mut child = *board;
+            child.make_possible_move(&mov);
+            let outcome = self.probe(&child)?;
+            let rank = match outcome{
+                Outcome::Loss(plies)=>1000 - plies as i64,
+                Outcome::Draw=>0,
+                Outcome::Win(plies)=>-1000 + plies as i64,
+            };
+            if best.as_ref().is_none_or(|b| rank > b.0){
+                best = Some((rank,mov,outcome));
+            }
+        }
+        best.map(|(_,mov,outcome)| (mov,outcome))
+    }
+
+    pub fn to_bytes(&self,name:&str)->Option<Vec<u8>>{
+        let table = self.tables.get(name)?;
+        let mut res = MAGIC.to_vec();
+        res.push(VERSION);
+        res.push(name.len() as u8);
+        res.extend_from_slice(name.as_bytes());
+        res.extend_from_slice(&(table.values.len() as u32).to_le_bytes());
+
+        let mut i = 0;
+        while i < table.values.len(){
+            let value = table.values[i];
+            let mut run = 1;
+            while run < 255 && i + run < table.values.len() && table.values[i + run] == value{
+                run += 1;
+            }
+            res.push(run as u8);
+            res.push(value);
+            i += run;
+        }
+        Some(res)
+    }
+
+    // loads a table written by to_bytes, returns its name
+    pub fn add_bytes(&mut self,bytes:&[u8])->Option<String>{
+        if bytes.get(0..4)? != MAGIC || *bytes.get(4)? != VERSION{
+            return None;
+        }
+        let name_len = *bytes.get(5)? as usize;
+        let name = std::str::from_utf8(bytes.get(6..6 + name_len)?).ok()?.to_string();
+        let layout = Layout::new(&name)?;
+        let mut pos = 6 + name_len;
+        let size = u32::from_le_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        pos += 4;
+        if size != layout.size{
+            return None;
+        }
+
+        let mut values = Vec::with_capacity(size);
+        for pair in bytes[pos..].chunks(2){
+            let [run,value] = pair else{return None};
+            values.extend(std::iter::repeat_n(*value,*run as usize));
+        }
+        if values.len() != size{
+            return None;
+        }
+        self.tables.insert(name.clone(),Table{layout,values});
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn pieces_of(board:&Board)->Vec<(usize,Color,Piece)>{
+        let mut pieces = vec![];
+        for (i,tile) in board.data.iter().enumerate(){
+            if let Tile::Taken(color,piece,_) = tile{
+                pieces.push((i,*color,*piece));
+            }
+        }
+        pieces
+    }
+
+    // every legal slot decodes to a position whose index leads back to an equivalent position, and the
+    // mirror images of a position share its index
+    #[test]
+    fn index_round_trip(){
+        for name in ["KRvK","KRvKN","KRvKP","KRRvK","KNNvK","KPPvK"]{
+            let layout = Layout::new(name).unwrap();
+            let symmetries:&[u8] = if layout.pawns {&[1]} else {&[1,2,3,4,5,6,7]};
+            let mut checked = 0;
+            for idx in (0..layout.size).step_by(layout.size / 5000){
+                let Some(board) = layout.board(idx) else{continue};
+                let canonical = layout.index(&board);
+                assert_eq!(layout.index(&layout.board(canonical).unwrap()),canonical,"{} {}",name,board.to_fen());
+                for &symmetry in symmetries{
+                    let pieces:Vec<_> = pieces_of(&board).into_iter().map(|(sq,c,p)| (transform(sq,symmetry),c,p)).collect();
+                    let mirrored = make_board(&pieces,board.counter % 2 == 1);
+                    assert_eq!(layout.index(&mirrored),canonical,"{} {}",name,board.to_fen());
+                }
+                checked += 1;
+            }
+            assert!(checked > 1000);
+        }
+    }
+
+    #[test]
+    fn known_outcomes(){
+        let mut tables = EndgameTables::new();
+        assert!(tables.generate("KQvK"));
+        assert!(tables.generate("KRvK"));
+        assert_eq!(tables.names(),["KQvK","KRvK"]);
+        let probe = |fen:&str| tables.probe(&Board::from_fen(fen).unwrap()).unwrap();
+
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1"),Outcome::Win(1));
+        assert_eq!(probe("k7/1Q6/1K6/8/8/8/8/8 b - - 0 1"),Outcome::Loss(0));
+        assert_eq!(probe("7K/8/8/8/8/8/8/kQ6 b - - 0 1"),Outcome::Draw);
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"),Outcome::Win(1));
+        // the same material with the colors swapped
+        assert_eq!(probe("7r/8/8/8/8/1k6/8/K7 b - - 0 1"),Outcome::Win(1));
+
+        // the longest mates are 10 moves with a queen and 16 with a rook
+        for (name,longest) in [("KQvK",19),("KRvK",31)]{
+            let table = &tables.tables[name];
+            let max = table.values.iter().map(|v| Outcome::from_byte(*v)).filter_map(|outcome| match outcome{
+                Outcome::Win(plies)=>Some(plies),
+                _=>None,
+            }).max();
+            assert_eq!(max,Some(longest),"{}",name);
+        }
+
+        let board = Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        let (mov,outcome) = tables.best_move(&board).unwrap();
+        assert_eq!(outcome,Outcome::Loss(0));
+        assert_eq!(board.san(&mov),"Rh8#");
+    }
+
+    // mirror images with two identical pieces, which the symmetry puts in the other order
+    #[test]
+    fn identical_pieces(){
+        let mut tables = EndgameTables::new();
+        assert!(tables.generate("KRRvK"));
+        let probe = |fen:&str| tables.probe(&Board::from_fen(fen).unwrap()).unwrap();
+        for (fen,mirrored) in [("7k/8/8/8/4RR2/8/8/2K5 w - - 0 1","k7/8/8/8/2RR4/8/8/5K2 w - - 0 1"),
+            ("7k/8/8/8/8/8/8/2K1RR2 w - - 0 1","k7/8/8/8/8/8/8/2RR1K2 w - - 0 1")]{
+            assert!(matches!(probe(fen),Outcome::Win(_)),"{}",fen);
+            assert_eq!(probe(fen),probe(mirrored),"{}",fen);
+        }
+        // two rooks mate in at most 7 moves
+        let max = tables.tables["KRRvK"].values.iter().filter_map(|v| match Outcome::from_byte(*v){
+            Outcome::Win(plies)=>Some(plies),
+            _=>None,
+        }).max();
+        assert_eq!(max,Some(13));
+    }
+
+    #[test]
+    fn bytes_round_trip(){
+        let mut tables = EndgameTables::new();
+        assert!(tables.generate("KRvK"));
+        let bytes = tables.to_bytes("KRvK").unwrap();
+        let mut loaded = EndgameTables::new();
+        assert_eq!(loaded.add_bytes(&bytes).as_deref(),Some("KRvK"));
+        assert!(loaded.tables["KRvK"].values == tables.tables["KRvK"].values);
+        assert!(loaded.add_bytes(&bytes[..bytes.len() - 1]).is_none());
+    }
+}
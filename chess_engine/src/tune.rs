@@ -0,0 +1,203 @@
+// Texel tuning: fits the evaluation parameters to positions labelled with the result of the game they
+// come from. An evaluation becomes an expected result through a logistic curve, and the parameters are
+// moved to shrink the squared difference to the real results over all positions.
+//
+// With the game phase fixed, the evaluation is linear in its weights, so every position is
+// turned into a list of coefficients once and the gradient is exact. Parameters are optimised with Adam.
+// The positions should be quiet, the static evaluation knows nothing about pieces about to be taken.
+
+use crate::*;
+use crate::eval::{self,Collector,Term,Weight};
+use crate::params::{EvalParams,Pair};
+
+const PIECES:[Piece;6] = [Piece::Pawn,Piece::Knight,Piece::Bishop,Piece::Rook,Piece::Queen,Piece::King];
+// the values come first, then the 64 squares of every piece, then pawn structure, king safety, mobility
+// and threats
+const WEIGHTS:usize = 6 + 6 * 64 + 8 + 8 + 5 + 4 + 6 + 1 + 6 + 2;
+
+const ADAM_BETA1:f64 = 0.9;
+const ADAM_BETA2:f64 = 0.999;
+const ADAM_EPSILON:f64 = 1e-8;
+
+fn slot(piece:Piece)->usize{
+    PIECES.iter().position(|p| std::mem::discriminant(p) == std::mem::discriminant(&piece)).unwrap()
+}
+
+fn index(weight:Weight)->usize{
+    let pawns = 6 + 6 * 64;
+    match weight{
+        Weight::Value(piece)=>slot(piece),
+        Weight::Square(piece,sq)=>6 + slot(piece) * 64 + sq,
+        Weight::Passed(rank)=>pawns + rank,
+        Weight::Connected(rank)=>pawns + 8 + rank,
+        Weight::Doubled=>pawns + 16,
+        Weight::Isolated=>pawns + 17,
+        Weight::Backward=>pawns + 18,
+        Weight::PassedOwnKing=>pawns + 19,
+        Weight::PassedEnemyKing=>pawns + 20,
+        Weight::ShieldClose=>pawns + 21,
+        Weight::ShieldFar=>pawns + 22,
+        Weight::KingOpenFile=>pawns + 23,
+        Weight::KingHalfOpenFile=>pawns + 24,
+        Weight::KingAttack(piece)=>pawns + 25 + slot(piece),
+        Weight::KingPawnDistance=>pawns + 31,
+        Weight::Mobility(piece)=>pawns + 32 + slot(piece),
+        Weight::PawnThreat=>pawns + 38,
+        Weight::Hanging=>pawns + 39,
+    }
+}
+
+// every weight in the order of index
+fn all_weights()->Vec<Weight>{
+    let mut res:Vec<Weight> = PIECES.into_iter().map(Weight::Value).collect();
+    res.extend(PIECES.into_iter().flat_map(|piece| (0..64).map(move |sq| Weight::Square(piece,sq))));
+    res.extend((0..8).map(Weight::Passed));
+    res.extend((0..8).map(Weight::Connected));
+    res.extend([Weight::Doubled,Weight::Isolated,Weight::Backward,Weight::PassedOwnKing,Weight::PassedEnemyKing]);
+    res.extend([Weight::ShieldClose,Weight::ShieldFar,Weight::KingOpenFile,Weight::KingHalfOpenFile]);
+    res.extend(PIECES.into_iter().map(Weight::KingAttack));
+    res.push(Weight::KingPawnDistance);
+    res.extend(PIECES.into_iter().map(Weight::Mobility));
+    res.extend([Weight::PawnThreat,Weight::Hanging]);
+    res
+}
+
+fn sigmoid(x:f64)->f64{
+    1. / (1. + (-x).exp())
+}
+
+// what one position contributes: white minus black count of every weight it uses
+struct Entry{
+    // share of the midgame score, 1 at the start
+    mg_share:f64,
+    coefficients:Vec<(usize,f64)>,
+    result:f64,
+}
+
+impl Collector for Entry{
+    fn add(&mut self,_:Term,weight:Weight,color:Color,count:i32){
+        let count = if color == Color::White {count as f64} else {-count as f64};
+        let i = index(weight);
+        match self.coefficients.iter_mut().find(|(j,_)| *j == i){
+            Some((_,c))=>*c += count,
+            None=>self.coefficients.push((i,count)),
+        }
+    }
+}
+
+pub struct Tuner{
+    params:EvalParams,
+    entries:Vec<Entry>,
+    // midgame and endgame value of every weight
+    weights:Vec<[f64;2]>,
+    // centipawns per logistic unit
+    scale:f64,
+    // Adam's running moments and step count
+    moments:Vec<[f64;4]>,
+    steps:i32,
+}
+
+impl Tuner{
+
+    // starts from these parameters, the phase weights and the exploration are kept as they are
+    pub fn new(params:&EvalParams)->Tuner{
+        let mut weights = vec![[0.;2];WEIGHTS];
+        for weight in all_weights(){
+            let Pair{mg,eg} = params.weight(weight);
+            weights[index(weight)] = [mg as f64,eg as f64];
+        }
+        Tuner{params:params.clone(),entries:vec![],weights,scale:275.,moments:vec![[0.;4];WEIGHTS],steps:0}
+    }
+
+    // result for white: 1 for a win, 0.5 for a draw, 0 for a loss
+    pub fn add_position(&mut self,board:&Board,result:f64){
+        let mut entry = Entry{
+            mg_share:eval::game_phase(board,&self.params) as f64 / self.params.max_phase() as f64,
+            coefficients:vec![],
+            result,
+        };
+        eval::collect(board,&mut entry);
+        entry.coefficients.retain(|(_,c)| *c != 0.);
+        self.entries.push(entry);
+    }
+
+    pub fn len(&self)->usize{
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.entries.is_empty()
+    }
+
+    pub fn scale(&self)->f64{
+        self.scale
+    }
+
+    fn evaluate(&self,entry:&Entry)->f64{
+        entry.coefficients.iter().map(|(i,c)| {
+            let [mg,eg] = self.weights[*i];
+            c * (mg * entry.mg_share + eg * (1. - entry.mg_share))
+        }).sum()
+    }
+
+    fn error_at(&self,scale:f64)->f64{
+        let total:f64 = self.entries.iter().map(|entry| (entry.result - sigmoid(self.evaluate(entry) / scale)).powi(2)).sum();
+        total / self.entries.len().max(1) as f64
+    }
+
+    // mean squared difference between expected and real results
+    pub fn error(&self)->f64{
+        self.error_at(self.scale)
+    }
+
+    // picks the logistic scale that fits the current parameters best, before tuning them
+    pub fn fit_scale(&mut self)->f64{
+        // golden section search, the error has a single minimum in the scale
+        let ratio = (5f64.sqrt() - 1.) / 2.;
+        let (mut lo,mut hi) = (10.,2000.);
+        for _ in 0..60{
+            let a = hi - ratio * (hi - lo);
+            let b = lo + ratio * (hi - lo);
+            if self.error_at(a) < self.error_at(b) {hi = b} else {lo = a}
+        }
+        self.scale = (lo + hi) / 2.;
+        self.scale
+    }
+
+    // one pass over all positions, rate is roughly the most centipawns a weight moves
+    pub fn step(&mut self,rate:f64){
+        let mut gradient = vec![[0.;2];WEIGHTS];
+        let n = self.entries.len().max(1) as f64;
+        for entry in self.entries.iter(){
+            let p = sigmoid(self.evaluate(entry) / self.scale);
+            let g = 2. * (p - entry.result) * p * (1. - p) / self.scale / n;
+            for (i,c) in entry.coefficients.iter(){
+                gradient[*i][0] += g * c * entry.mg_share;
+                gradient[*i][1] += g * c * (1. - entry.mg_share);
+            }
+        }
+
+        self.steps += 1;
+        let correction1 = 1. - ADAM_BETA1.powi(self.steps);
+        let correction2 = 1. - ADAM_BETA2.powi(self.steps);
+        for (i,pair) in gradient.iter().enumerate(){
+            for (k,g) in pair.iter().enumerate(){
+                let moments = &mut self.moments[i];
+                moments[k] = ADAM_BETA1 * moments[k] + (1. - ADAM_BETA1) * g;
+                moments[k + 2] = ADAM_BETA2 * moments[k + 2] + (1. - ADAM_BETA2) * g * g;
+                let (m,v) = (moments[k] / correction1,moments[k + 2] / correction2);
+                self.weights[i][k] -= rate * m / (v.sqrt() + ADAM_EPSILON);
+            }
+        }
+    }
+
+    // the tuned weights rounded to centipawns
+    pub fn params(&self)->EvalParams{
+        let mut params = self.params.clone();
+        for weight in all_weights(){
+            let [mg,eg] = self.weights[index(weight)];
+            params.set_weight(weight,Pair{mg:mg.round() as i32,eg:eg.round() as i32});
+        }
+        params
+    }
+}